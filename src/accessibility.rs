@@ -0,0 +1,312 @@
+//! Help for players who can't tell the statue colors apart. Each
+//! `StatueColor` gets a shape that is the same everywhere it is shown:
+//!
+//! - red is a square, a cube in the room
+//! - green is a disc, a ball in the room
+//! - blue is a ring, a torus in the room
+//!
+//! With `Settings::color_symbols` the shapes float over the statues and in
+//! front of revealed UV posters, and mark the inventory icons. With
+//! `Settings::color_names` looking at a statue or poster names its color
+//! under the crosshair. `Settings::high_contrast` switches the HUD to
+//! white on black.
+
+use crate::{
+    has_window, localization::Localization, settings::Settings, BallStatue, BlacklightTarget,
+    PlacedStatue, Player, Poster, StatueColor, Target,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_flycam::FlyCam;
+
+/// how far over a statue's origin its marker floats
+const STATUE_MARKER_HEIGHT: f32 = 0.4;
+/// how far in front of a poster, towards the camera, its marker floats
+const POSTER_MARKER_DISTANCE: f32 = 0.15;
+
+pub fn color_name_key(color: StatueColor) -> &'static str {
+    match color {
+        StatueColor::Red => "color-red",
+        StatueColor::Green => "color-green",
+        StatueColor::Blue => "color-blue",
+    }
+}
+
+/// Paints the shape for `color` inside `rect`, white with a black outline so
+/// it reads on any background.
+pub fn paint_symbol(painter: &egui::Painter, rect: egui::Rect, color: StatueColor) {
+    let center = rect.center();
+    let radius = rect.width().min(rect.height()) * 0.4;
+    let outline = egui::Stroke::new(2.0, egui::Color32::BLACK);
+    match color {
+        StatueColor::Red => {
+            let square = egui::Rect::from_center_size(center, egui::Vec2::splat(radius * 1.6));
+            painter.rect(square, 0.0, egui::Color32::WHITE, outline);
+        }
+        StatueColor::Green => {
+            painter.circle(center, radius, egui::Color32::WHITE, outline);
+        }
+        StatueColor::Blue => {
+            let width = radius * 0.4;
+            painter.circle_stroke(
+                center,
+                radius - width / 2.0,
+                egui::Stroke::new(width + 4.0, egui::Color32::BLACK),
+            );
+            painter.circle_stroke(
+                center,
+                radius - width / 2.0,
+                egui::Stroke::new(width, egui::Color32::WHITE),
+            );
+        }
+    }
+}
+
+struct MarkerAssets {
+    square: Handle<Mesh>,
+    disc: Handle<Mesh>,
+    ring: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+impl MarkerAssets {
+    fn mesh(&self, color: StatueColor) -> Handle<Mesh> {
+        match color {
+            StatueColor::Red => self.square.clone(),
+            StatueColor::Green => self.disc.clone(),
+            StatueColor::Blue => self.ring.clone(),
+        }
+    }
+}
+
+enum MarkerPlacement {
+    AboveStatue,
+    FacingCamera,
+}
+
+/// A shape floating by a statue or poster. It is shown while `mesh`, a mesh
+/// of the thing it marks, is, and despawned along with it.
+struct ColorMarker {
+    mesh: Entity,
+    placement: MarkerPlacement,
+}
+
+fn setup_markers(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(MarkerAssets {
+        square: meshes.add(Mesh::from(shape::Cube { size: 0.12 })),
+        disc: meshes.add(Mesh::from(shape::Icosphere {
+            radius: 0.07,
+            subdivisions: 2,
+        })),
+        ring: meshes.add(Mesh::from(shape::Torus {
+            radius: 0.07,
+            ring_radius: 0.025,
+            subdivisions_segments: 24,
+            subdivisions_sides: 12,
+        })),
+        material: materials.add(StandardMaterial {
+            base_color: Color::WHITE,
+            unlit: true,
+            ..Default::default()
+        }),
+    });
+}
+
+fn add_color_markers(
+    mut commands: Commands,
+    assets: Res<MarkerAssets>,
+    statues: Query<(&BallStatue, &Children), Added<BallStatue>>,
+    placed_statues: Query<(&PlacedStatue, &Children), Added<PlacedStatue>>,
+    posters: Query<(Entity, &Poster), Added<Poster>>,
+) {
+    let mut spawn = |mesh: Entity, color: StatueColor, placement: MarkerPlacement| {
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: assets.mesh(color),
+                material: assets.material.clone(),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: false,
+                },
+                ..Default::default()
+            })
+            .insert(ColorMarker { mesh, placement });
+    };
+    for (statue, children) in statues.iter() {
+        if let Some(mesh) = children.first() {
+            spawn(*mesh, statue.0, MarkerPlacement::AboveStatue);
+        }
+    }
+    // the meshes of a placed statue carry `PlacedStatue` too, only the node
+    // has children
+    for (statue, children) in placed_statues.iter() {
+        if let Some(mesh) = children.first() {
+            spawn(*mesh, statue.0, MarkerPlacement::AboveStatue);
+        }
+    }
+    // the plain posters are always in view, only the clue revealed by the
+    // blacklight is marked
+    for (mesh, poster) in posters.iter() {
+        if poster.2 {
+            spawn(mesh, poster.1, MarkerPlacement::FacingCamera);
+        }
+    }
+}
+
+fn update_color_markers(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut markers: Query<(Entity, &ColorMarker, &mut Transform, &mut Visible)>,
+    meshes: Query<(&GlobalTransform, &Visible), Without<ColorMarker>>,
+    camera: Query<&Transform, (With<FlyCam>, Without<ColorMarker>)>,
+) {
+    let eye = camera.iter().next().map(|transform| transform.translation);
+    for (entity, marker, mut transform, mut visible) in markers.iter_mut() {
+        let (mesh_transform, mesh_visible) = match meshes.get(marker.mesh) {
+            Ok(mesh) => mesh,
+            Err(_) => {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        };
+        visible.is_visible = settings.color_symbols && mesh_visible.is_visible;
+        let origin = mesh_transform.translation;
+        transform.translation = match (&marker.placement, eye) {
+            (MarkerPlacement::FacingCamera, Some(eye)) => {
+                origin + (eye - origin).normalize_or_zero() * POSTER_MARKER_DISTANCE
+            }
+            _ => origin + Vec3::Y * STATUE_MARKER_HEIGHT,
+        };
+    }
+}
+
+/// The color of what the crosshair is on, a statue lying around, a placed
+/// statue or, with the blacklight out, a poster.
+fn targeted_color(
+    target: &Target,
+    blacklight_target: &BlacklightTarget,
+    player: &Player,
+    parents: &Query<&Parent>,
+    statues: &Query<&BallStatue>,
+    placed_statues: &Query<&PlacedStatue>,
+    posters: &Query<&Poster>,
+) -> Option<StatueColor> {
+    if let Some(target) = target.0.as_ref() {
+        if let Ok(placed) = placed_statues.get(target.entity) {
+            return Some(placed.0);
+        }
+        let statue = parents
+            .get(target.entity)
+            .ok()
+            .and_then(|parent| statues.get(parent.0).ok());
+        if let Some(statue) = statue {
+            return Some(statue.0);
+        }
+    }
+    if player.equipped_name() == "InvBlacklightFlashlight" {
+        if let Some(target) = blacklight_target.0.as_ref() {
+            return posters.get(target.entity).ok().map(|poster| poster.1);
+        }
+    }
+    None
+}
+
+#[allow(clippy::too_many_arguments)]
+fn color_tooltip(
+    egui_context: Res<EguiContext>,
+    settings: Res<Settings>,
+    localization: Res<Localization>,
+    target: Res<Target>,
+    blacklight_target: Res<BlacklightTarget>,
+    player: Res<Player>,
+    parents: Query<&Parent>,
+    statues: Query<&BallStatue>,
+    placed_statues: Query<&PlacedStatue>,
+    posters: Query<&Poster>,
+) {
+    if !settings.color_names {
+        return;
+    }
+    let color = targeted_color(
+        &target,
+        &blacklight_target,
+        &player,
+        &parents,
+        &statues,
+        &placed_statues,
+        &posters,
+    );
+    let color = match color {
+        Some(color) => color,
+        None => return,
+    };
+
+    egui::Window::new("color_tooltip")
+        .title_bar(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 40.0])
+        .show(egui_context.ctx(), |ui| {
+            ui.horizontal(|ui| {
+                if settings.color_symbols {
+                    let (rect, _) =
+                        ui.allocate_exact_size(egui::vec2(18.0, 18.0), egui::Sense::hover());
+                    paint_symbol(ui.painter(), rect, color);
+                }
+                ui.add(egui::Label::new(localization.tr(color_name_key(color))).strong());
+            });
+        });
+}
+
+fn high_contrast_visuals() -> egui::Visuals {
+    let mut visuals = egui::Visuals::dark();
+    visuals.override_text_color = Some(egui::Color32::WHITE);
+    visuals.widgets.noninteractive.bg_fill = egui::Color32::BLACK;
+    visuals.widgets.noninteractive.bg_stroke = egui::Stroke::new(2.0, egui::Color32::WHITE);
+    visuals.widgets.noninteractive.fg_stroke = egui::Stroke::new(1.5, egui::Color32::WHITE);
+    visuals.widgets.inactive.bg_fill = egui::Color32::from_gray(40);
+    visuals.widgets.inactive.bg_stroke = egui::Stroke::new(1.0, egui::Color32::WHITE);
+    visuals.widgets.hovered.bg_stroke = egui::Stroke::new(2.0, egui::Color32::YELLOW);
+    visuals.widgets.active.bg_stroke = egui::Stroke::new(2.0, egui::Color32::YELLOW);
+    visuals.selection.bg_fill = egui::Color32::from_rgb(0, 90, 200);
+    visuals.selection.stroke = egui::Stroke::new(1.5, egui::Color32::WHITE);
+    visuals.extreme_bg_color = egui::Color32::BLACK;
+    visuals
+}
+
+fn apply_contrast(egui_context: Res<EguiContext>, settings: Res<Settings>) {
+    if !settings.is_changed() {
+        return;
+    }
+    let visuals = if settings.high_contrast {
+        high_contrast_visuals()
+    } else {
+        egui::Visuals::dark()
+    };
+    egui_context.ctx().set_visuals(visuals);
+}
+
+/// Shape markings for the statue colors, color name tooltips and the high
+/// contrast HUD.
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(setup_markers.system());
+        app.add_system(add_color_markers.system());
+        app.add_system(update_color_markers.system());
+        app.add_system(
+            color_tooltip
+                .system()
+                .with_run_criteria(has_window.system()),
+        );
+        app.add_system(
+            apply_contrast
+                .system()
+                .with_run_criteria(has_window.system()),
+        );
+    }
+}
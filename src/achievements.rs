@@ -0,0 +1,267 @@
+//! Achievements. The campaign manifest lists them with the condition that
+//! unlocks each one; conditions are checked against puzzle attempts and
+//! poster reveals in whichever room the player is in. Unlocks pop up as a
+//! toast, are remembered between sessions and are listed next to the main
+//! menu.
+
+use crate::{
+    campaign::{Campaign, MainMenu},
+    console::ConsoleCommand,
+    game_time::GameTime,
+    has_window,
+    loading::LoadingAssets,
+    localization::Localization,
+    puzzle_graph::GoalProgress,
+    scene_ready::{SceneKind, SceneReady},
+    storage::{self, StorageConfig},
+    PuzzleAttempt,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use fluent_bundle::FluentArgs;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+
+const ACHIEVEMENTS_KEY: &str = "bevy_er_achievements";
+const TOAST_SECONDS: f32 = 4.0;
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum AchievementCondition {
+    /// solve a room without revealing any of its posters
+    SolveWithoutHints,
+    /// solve a room without a wrong attempt
+    SolveWithoutMistakes,
+    /// solve a room within this many seconds of entering it
+    SolveWithin(f64),
+    /// reveal this many posters in one room
+    RevealPosters(usize),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Achievement {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub condition: AchievementCondition,
+}
+
+/// The achievements from the manifest.
+#[derive(Default)]
+pub struct Achievements(pub Vec<Achievement>);
+
+/// Ids of the unlocked achievements, persisted between sessions.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UnlockedAchievements {
+    pub unlocked: HashSet<String>,
+}
+
+impl UnlockedAchievements {
+    pub fn load() -> Self {
+        storage::load(ACHIEVEMENTS_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self, storage_config: &StorageConfig) {
+        storage::save(storage_config, ACHIEVEMENTS_KEY, self);
+    }
+}
+
+pub struct AchievementUnlocked(pub String);
+
+/// How the current visit to a room is going. Console commands count as
+/// cheating, which rules out every achievement until the next room.
+#[derive(Default)]
+struct RoomVisit {
+    entered_at: f64,
+    wrong_attempts: usize,
+    cheated: bool,
+}
+
+#[derive(Default)]
+struct Toasts(VecDeque<(String, Timer)>);
+
+fn apply_achievements(
+    mut asset_events: EventReader<AssetEvent<Campaign>>,
+    campaigns: Res<Assets<Campaign>>,
+    mut achievements: ResMut<Achievements>,
+) {
+    for event in asset_events.iter() {
+        let handle = match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => handle,
+            AssetEvent::Removed { .. } => continue,
+        };
+        if let Some(campaign) = campaigns.get(handle) {
+            achievements.0 = campaign.achievements.clone();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn check_achievements(
+    storage_config: Res<StorageConfig>,
+    time: Res<GameTime>,
+    achievements: Res<Achievements>,
+    goals: Res<GoalProgress>,
+    mut unlocked: ResMut<UnlockedAchievements>,
+    mut visit: Local<RoomVisit>,
+    mut scenes_ready: EventReader<SceneReady>,
+    mut attempts: EventReader<PuzzleAttempt>,
+    mut cheats: EventReader<ConsoleCommand>,
+    mut unlocks: EventWriter<AchievementUnlocked>,
+) {
+    for ready in scenes_ready.iter() {
+        if let SceneKind::Room(_) = ready.kind {
+            *visit = RoomVisit {
+                entered_at: time.seconds_since_startup(),
+                ..Default::default()
+            };
+        }
+    }
+    if cheats.iter().count() > 0 {
+        visit.cheated = true;
+    }
+    if visit.cheated {
+        return;
+    }
+
+    let mut solved = false;
+    for attempt in attempts.iter() {
+        if attempt.correct {
+            solved = true;
+        } else {
+            visit.wrong_attempts += 1;
+        }
+    }
+    let seconds = time.seconds_since_startup() - visit.entered_at;
+    let mut changed = false;
+    for achievement in achievements.0.iter() {
+        if unlocked.unlocked.contains(&achievement.id) {
+            continue;
+        }
+        let earned = match achievement.condition {
+            AchievementCondition::SolveWithoutHints => solved && goals.revealed_posters.is_empty(),
+            AchievementCondition::SolveWithoutMistakes => solved && visit.wrong_attempts == 0,
+            AchievementCondition::SolveWithin(limit) => solved && seconds <= limit,
+            AchievementCondition::RevealPosters(count) => goals.revealed_posters.len() >= count,
+        };
+        if earned {
+            unlocked.unlocked.insert(achievement.id.clone());
+            unlocks.send(AchievementUnlocked(achievement.id.clone()));
+            changed = true;
+        }
+    }
+    if changed {
+        unlocked.save(&storage_config);
+    }
+}
+
+fn achievement_title(localization: &Localization, achievement: &Achievement) -> String {
+    localization.tr_or(
+        &format!("achievement-{}", achievement.id),
+        &achievement.title,
+    )
+}
+
+fn show_toasts(
+    time: Res<GameTime>,
+    egui_context: Res<EguiContext>,
+    achievements: Res<Achievements>,
+    localization: Res<Localization>,
+    mut unlocks: EventReader<AchievementUnlocked>,
+    mut toasts: Local<Toasts>,
+) {
+    for unlock in unlocks.iter() {
+        if let Some(achievement) = achievements.0.iter().find(|a| a.id == unlock.0) {
+            toasts.0.push_back((
+                achievement_title(&localization, achievement),
+                Timer::from_seconds(TOAST_SECONDS, false),
+            ));
+        }
+    }
+
+    // one at a time, in the order they were earned
+    let finished = match toasts.0.front_mut() {
+        Some((title, timer)) => {
+            egui::Window::new("achievement_toast")
+                .title_bar(false)
+                .resizable(false)
+                .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+                .show(egui_context.ctx(), |ui| {
+                    ui.label(localization.tr("achievement-unlocked"));
+                    ui.heading(title.as_str());
+                });
+            timer.tick(time.delta()).finished()
+        }
+        None => false,
+    };
+    if finished {
+        toasts.0.pop_front();
+    }
+}
+
+fn achievement_list(
+    egui_context: Res<EguiContext>,
+    menu: Res<MainMenu>,
+    loading: Res<LoadingAssets>,
+    achievements: Res<Achievements>,
+    unlocked: Res<UnlockedAchievements>,
+    localization: Res<Localization>,
+) {
+    if !menu.open || !loading.is_done() || achievements.0.is_empty() {
+        return;
+    }
+
+    let count = achievements
+        .0
+        .iter()
+        .filter(|achievement| unlocked.unlocked.contains(&achievement.id))
+        .count();
+    let mut args = FluentArgs::new();
+    args.set("unlocked", count);
+    args.set("total", achievements.0.len());
+    egui::Window::new(localization.tr_args("achievements-title", &args))
+        .id(egui::Id::new("achievements"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::LEFT_CENTER, [10.0, 0.0])
+        .show(egui_context.ctx(), |ui| {
+            for achievement in achievements.0.iter() {
+                let title = egui::Label::new(achievement_title(&localization, achievement));
+                let title = if unlocked.unlocked.contains(&achievement.id) {
+                    title.strong()
+                } else {
+                    title.weak()
+                };
+                let description = localization.tr_or(
+                    &format!("achievement-{}-description", achievement.id),
+                    &achievement.description,
+                );
+                ui.add(title).on_hover_text(description);
+            }
+        });
+}
+
+/// Achievements from the campaign manifest, with unlock toasts.
+pub struct AchievementsPlugin;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<AchievementUnlocked>();
+        app.init_resource::<StorageConfig>();
+        app.init_resource::<Achievements>();
+        app.insert_resource(UnlockedAchievements::load());
+        app.add_system(apply_achievements.system());
+        app.add_system(check_achievements.system().label("check_achievements"));
+        app.add_system(
+            show_toasts
+                .system()
+                .with_run_criteria(has_window.system())
+                .after("check_achievements"),
+        );
+        app.add_system(
+            achievement_list
+                .system()
+                .with_run_criteria(has_window.system()),
+        );
+    }
+}
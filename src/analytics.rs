@@ -0,0 +1,372 @@
+//! Playtest analytics. Pickups, placements, poster reveals, hints used,
+//! puzzle attempts, goals, console cheats and solve times are timestamped and
+//! kept as JSON Lines, one object per event:
+//!
+//!     {"t":12.5,"room":"classroom","event":"item_picked_up","item":"InvBlacklightFlashlight"}
+//!
+//! Revealing a poster with the blacklight counts as using a hint, as it does
+//! for the "without hints" achievement.
+//!
+//! Native builds append them to `EscapeRoomConfig::analytics_log`. Web
+//! builds send each batch to `EscapeRoomConfig::analytics_endpoint` with
+//! `sendBeacon` if one is set, and the summary offers the whole log as a
+//! download. The summary opens by itself once every room is completed, F7
+//! opens it earlier.
+
+use crate::{
+    campaign::CampaignProgress,
+    console::ConsoleCommand,
+    game_time::GameTime,
+    has_window,
+    heatmap::PathSamples,
+    interaction::{ItemPickedUp, ItemPlaced, ItemRemoved},
+    puzzle_graph::{GoalCompleted, GoalProgress},
+    rooms::{CurrentRoom, Rooms},
+    scene_ready::{SceneKind, SceneReady},
+    EscapeRoomConfig, PuzzleAttempt,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use serde::Serialize;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AnalyticsEvent {
+    RoomEntered,
+    ItemPickedUp {
+        item: String,
+    },
+    ItemPlaced {
+        item: String,
+        holder: String,
+    },
+    ItemRemoved {
+        item: String,
+    },
+    PosterRevealed {
+        poster: String,
+    },
+    /// the posters are the game's hints, so every reveal also logs this
+    HintUsed {
+        hint: String,
+    },
+    WrongAttempt,
+    GoalCompleted {
+        goal: String,
+    },
+    /// `seconds` counts from when the room was entered, pauses left out
+    RoomSolved {
+        seconds: f64,
+    },
+    Cheat {
+        command: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalyticsEntry {
+    /// seconds of play since the game started, pauses left out
+    pub t: f64,
+    pub room: Option<String>,
+    #[serde(flatten)]
+    pub event: AnalyticsEvent,
+}
+
+#[derive(Default)]
+pub struct Analytics {
+    pub entries: Vec<AnalyticsEntry>,
+    /// entries already written out
+    flushed: usize,
+    room_entered_at: Option<f64>,
+    revealed_posters: HashSet<String>,
+}
+
+impl Analytics {
+    fn log(&mut self, time: &GameTime, room: &CurrentRoom, event: AnalyticsEvent) {
+        self.entries.push(AnalyticsEntry {
+            t: time.seconds_since_startup(),
+            room: room.id.clone(),
+            event,
+        });
+    }
+}
+
+pub fn to_json_lines(entries: &[AnalyticsEntry]) -> String {
+    entries
+        .iter()
+        .filter_map(|entry| serde_json::to_string(entry).ok())
+        .map(|line| line + "\n")
+        .collect()
+}
+
+#[derive(Default)]
+pub struct AnalyticsSummary {
+    pub open: bool,
+    shown_at_end: bool,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn record_analytics(
+    time: Res<GameTime>,
+    current: Res<CurrentRoom>,
+    goals: Res<GoalProgress>,
+    mut analytics: ResMut<Analytics>,
+    mut scenes_ready: EventReader<SceneReady>,
+    mut picked_up: EventReader<ItemPickedUp>,
+    mut placed: EventReader<ItemPlaced>,
+    mut removed: EventReader<ItemRemoved>,
+    mut attempts: EventReader<PuzzleAttempt>,
+    mut completed: EventReader<GoalCompleted>,
+    mut cheats: EventReader<ConsoleCommand>,
+) {
+    for ready in scenes_ready.iter() {
+        if let SceneKind::Room(_) = ready.kind {
+            analytics.room_entered_at = Some(time.seconds_since_startup());
+            analytics.revealed_posters = goals.revealed_posters.clone();
+            analytics.log(&time, &current, AnalyticsEvent::RoomEntered);
+        }
+    }
+    for event in picked_up.iter() {
+        let item = event.item.clone();
+        analytics.log(&time, &current, AnalyticsEvent::ItemPickedUp { item });
+    }
+    for event in placed.iter() {
+        let event = AnalyticsEvent::ItemPlaced {
+            item: event.item.clone(),
+            holder: event.holder.clone(),
+        };
+        analytics.log(&time, &current, event);
+    }
+    for event in removed.iter() {
+        let item = event.item.clone();
+        analytics.log(&time, &current, AnalyticsEvent::ItemRemoved { item });
+    }
+    let mut revealed: Vec<_> = goals
+        .revealed_posters
+        .difference(&analytics.revealed_posters)
+        .cloned()
+        .collect();
+    revealed.sort();
+    for poster in revealed {
+        analytics.revealed_posters.insert(poster.clone());
+        let hint = poster.clone();
+        analytics.log(&time, &current, AnalyticsEvent::PosterRevealed { poster });
+        analytics.log(&time, &current, AnalyticsEvent::HintUsed { hint });
+    }
+    for attempt in attempts.iter() {
+        if attempt.correct {
+            let seconds = analytics
+                .room_entered_at
+                .map(|entered_at| time.seconds_since_startup() - entered_at)
+                .unwrap_or_default();
+            analytics.log(&time, &current, AnalyticsEvent::RoomSolved { seconds });
+        } else {
+            analytics.log(&time, &current, AnalyticsEvent::WrongAttempt);
+        }
+    }
+    for goal in completed.iter() {
+        let goal = goal.0.clone();
+        analytics.log(&time, &current, AnalyticsEvent::GoalCompleted { goal });
+    }
+    for command in cheats.iter() {
+        let command = format!("{:?}", command);
+        analytics.log(&time, &current, AnalyticsEvent::Cheat { command });
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_out(config: &EscapeRoomConfig, lines: &str) {
+    use std::io::Write;
+
+    if config.analytics_log.is_empty() {
+        return;
+    }
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&config.analytics_log);
+    if let Err(err) = file.and_then(|mut file| file.write_all(lines.as_bytes())) {
+        error!("unable to write {}: {}", config.analytics_log, err);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_out(config: &EscapeRoomConfig, lines: &str) {
+    let endpoint = match config.analytics_endpoint.as_ref() {
+        Some(endpoint) => endpoint,
+        None => return,
+    };
+    let sent = web_sys::window()
+        .and_then(|window| {
+            window
+                .navigator()
+                .send_beacon_with_opt_str(endpoint, Some(lines))
+                .ok()
+        })
+        .unwrap_or(false);
+    if !sent {
+        error!("unable to send analytics to {}", endpoint);
+    }
+}
+
+/// Offers `contents` as a file download.
+#[cfg(target_arch = "wasm32")]
+fn download(file_name: &str, mime_type: &str, contents: &str) {
+    use wasm_bindgen::JsCast;
+
+    let result = (|| -> Result<(), wasm_bindgen::JsValue> {
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or("no document")?;
+        let parts = js_sys::Array::of1(&wasm_bindgen::JsValue::from_str(contents));
+        let blob = web_sys::Blob::new_with_str_sequence_and_options(
+            &parts,
+            web_sys::BlobPropertyBag::new().type_(mime_type),
+        )?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+        let link: web_sys::HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+        link.set_href(&url);
+        link.set_download(file_name);
+        link.click();
+        web_sys::Url::revoke_object_url(&url)
+    })();
+    if let Err(err) = result {
+        error!("unable to download {}: {:?}", file_name, err);
+    }
+}
+
+fn flush_analytics(config: Res<EscapeRoomConfig>, mut analytics: ResMut<Analytics>) {
+    if analytics.flushed == analytics.entries.len() {
+        return;
+    }
+    let lines = to_json_lines(&analytics.entries[analytics.flushed..]);
+    write_out(&config, &lines);
+    analytics.flushed = analytics.entries.len();
+}
+
+fn open_summary(
+    keyboard_input: Res<Input<KeyCode>>,
+    rooms: Res<Rooms>,
+    progress: Res<CampaignProgress>,
+    mut summary: ResMut<AnalyticsSummary>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F7) {
+        summary.open = !summary.open;
+    }
+    let finished = !rooms.definitions.is_empty()
+        && rooms
+            .definitions
+            .iter()
+            .all(|room| progress.is_completed(&room.id));
+    if finished && !summary.shown_at_end {
+        summary.shown_at_end = true;
+        summary.open = true;
+    }
+}
+
+/// Per room counts for the summary.
+#[derive(Default)]
+struct RoomSummary {
+    pickups: usize,
+    hints: usize,
+    wrong_attempts: usize,
+    cheats: usize,
+    solved_in: Option<f64>,
+}
+
+fn analytics_summary(
+    egui_context: Res<EguiContext>,
+    rooms: Res<Rooms>,
+    analytics: Res<Analytics>,
+    path: Res<PathSamples>,
+    mut summary: ResMut<AnalyticsSummary>,
+) {
+    if !summary.open {
+        return;
+    }
+
+    egui::Window::new("Playtest summary")
+        .open(&mut summary.open)
+        .default_width(400.0)
+        .show(egui_context.ctx(), |ui| {
+            egui::Grid::new("analytics").striped(true).show(ui, |ui| {
+                ui.label("Room");
+                ui.label("Solved in");
+                ui.label("Wrong attempts");
+                ui.label("Pickups");
+                ui.label("Hints");
+                ui.label("Cheats");
+                ui.end_row();
+                for room in rooms.definitions.iter() {
+                    let mut counts = RoomSummary::default();
+                    let entries = analytics
+                        .entries
+                        .iter()
+                        .filter(|entry| entry.room.as_deref() == Some(room.id.as_str()));
+                    for entry in entries {
+                        match &entry.event {
+                            AnalyticsEvent::ItemPickedUp { .. } => counts.pickups += 1,
+                            AnalyticsEvent::HintUsed { .. } => counts.hints += 1,
+                            AnalyticsEvent::WrongAttempt => counts.wrong_attempts += 1,
+                            AnalyticsEvent::Cheat { .. } => counts.cheats += 1,
+                            AnalyticsEvent::RoomSolved { seconds } => {
+                                counts.solved_in = counts.solved_in.or(Some(*seconds))
+                            }
+                            _ => {}
+                        }
+                    }
+                    ui.label(room.title.as_str());
+                    match counts.solved_in {
+                        Some(seconds) => ui.label(format!(
+                            "{}:{:02}",
+                            seconds as u64 / 60,
+                            seconds as u64 % 60
+                        )),
+                        None => ui.label("-"),
+                    };
+                    ui.label(counts.wrong_attempts.to_string());
+                    ui.label(counts.pickups.to_string());
+                    ui.label(counts.hints.to_string());
+                    ui.label(counts.cheats.to_string());
+                    ui.end_row();
+                }
+            });
+            ui.label(format!(
+                "{} events logged, {} path samples",
+                analytics.entries.len(),
+                path.samples.len()
+            ));
+            #[cfg(target_arch = "wasm32")]
+            {
+                if ui.button("Download log").clicked() {
+                    download(
+                        "bevy_er_analytics.jsonl",
+                        "application/x-ndjson",
+                        &to_json_lines(&analytics.entries),
+                    );
+                }
+                if ui.button("Download path").clicked() {
+                    download("bevy_er_path.csv", "text/csv", &path.to_csv());
+                }
+            }
+        });
+}
+
+/// Logs gameplay events for playtests, F7 shows a summary.
+pub struct AnalyticsPlugin;
+
+impl Plugin for AnalyticsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Analytics>();
+        app.init_resource::<AnalyticsSummary>();
+        app.add_system(record_analytics.system().label("record_analytics"));
+        app.add_system(flush_analytics.system().after("record_analytics"));
+        app.add_system(open_summary.system());
+        app.add_system(
+            analytics_summary
+                .system()
+                .with_run_criteria(has_window.system()),
+        );
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{
+    interaction::{ItemPickedUp, ItemPlaced, ItemRemoved},
+    settings::Settings,
+    Player,
+};
+use bevy::prelude::*;
+use bevy_flycam::FlyCam;
+use serde::Deserialize;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native;
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+/// label of the systems filling `PendingSounds` and `LoopLevels`, backends
+/// run after it
+const AUDIO_RESOLVE: &str = "audio_resolve";
+
+/// distance at which a positioned sound plays at half volume
+const HALF_VOLUME_DISTANCE: f32 = 6.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum SoundEffect {
+    Pickup,
+    Place,
+    WrongPlacement,
+    DoorCreak,
+}
+
+impl SoundEffect {
+    pub fn path(self) -> &'static str {
+        match self {
+            SoundEffect::Pickup => "sounds/pickup.wav",
+            SoundEffect::Place => "sounds/place.wav",
+            SoundEffect::WrongPlacement => "sounds/wrong.wav",
+            SoundEffect::DoorCreak => "sounds/door_creak.wav",
+        }
+    }
+}
+
+pub const SOUND_EFFECTS: &[SoundEffect] = &[
+    SoundEffect::Pickup,
+    SoundEffect::Place,
+    SoundEffect::WrongPlacement,
+    SoundEffect::DoorCreak,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundLoop {
+    Ambience,
+    BlacklightHum,
+}
+
+impl SoundLoop {
+    pub fn path(self) -> &'static str {
+        match self {
+            SoundLoop::Ambience => "sounds/ambience.wav",
+            SoundLoop::BlacklightHum => "sounds/blacklight_hum.wav",
+        }
+    }
+}
+
+pub const SOUND_LOOPS: &[SoundLoop] = &[SoundLoop::Ambience, SoundLoop::BlacklightHum];
+
+/// Request to play a one shot sound. With an emitter the sound is attenuated
+/// and panned relative to the camera, without one it plays at the listener.
+#[derive(Debug, Clone, Copy)]
+pub struct PlaySound {
+    pub effect: SoundEffect,
+    pub emitter: Option<Entity>,
+}
+
+impl PlaySound {
+    pub fn at_listener(effect: SoundEffect) -> Self {
+        Self {
+            effect,
+            emitter: None,
+        }
+    }
+
+    pub fn at(effect: SoundEffect, emitter: Entity) -> Self {
+        Self {
+            effect,
+            emitter: Some(emitter),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AudioMixer {
+    pub master: f32,
+    pub effects: f32,
+    pub ambience: f32,
+    pub muted: bool,
+    /// set while the game is paused
+    pub paused: bool,
+    /// Browsers refuse to play audio before the first user gesture, so on the
+    /// web nothing is played until a key or mouse button has been pressed.
+    pub unlocked: bool,
+}
+
+impl Default for AudioMixer {
+    fn default() -> Self {
+        Self {
+            master: 1.0,
+            effects: 1.0,
+            ambience: 0.5,
+            muted: false,
+            paused: false,
+            unlocked: !cfg!(target_arch = "wasm32"),
+        }
+    }
+}
+
+impl AudioMixer {
+    fn output(&self, channel: f32) -> f32 {
+        if self.muted || self.paused || !self.unlocked {
+            0.0
+        } else {
+            self.master * channel
+        }
+    }
+
+    pub fn effects_volume(&self) -> f32 {
+        self.output(self.effects)
+    }
+
+    pub fn ambience_volume(&self) -> f32 {
+        self.output(self.ambience)
+    }
+}
+
+/// A one shot sound with its final volume and panning, 0.0 is hard left and
+/// 1.0 hard right.
+#[derive(Debug, Clone, Copy)]
+pub struct ResolvedSound {
+    pub effect: SoundEffect,
+    pub volume: f32,
+    pub panning: f32,
+}
+
+/// Sounds resolved this frame, drained by the platform backend.
+#[derive(Default)]
+pub struct PendingSounds(pub Vec<ResolvedSound>);
+
+/// Target volume of each looping sound, zero means stopped.
+#[derive(Default)]
+pub struct LoopLevels {
+    pub ambience: f32,
+    pub blacklight_hum: f32,
+}
+
+impl LoopLevels {
+    pub fn level(&self, sound_loop: SoundLoop) -> f32 {
+        match sound_loop {
+            SoundLoop::Ambience => self.ambience,
+            SoundLoop::BlacklightHum => self.blacklight_hum,
+        }
+    }
+}
+
+fn spatialize(listener: &GlobalTransform, emitter: Vec3) -> (f32, f32) {
+    let offset = emitter - listener.translation;
+    let distance = offset.length();
+    let attenuation = 1.0 / (1.0 + (distance / HALF_VOLUME_DISTANCE).powi(2));
+    let panning = if distance > f32::EPSILON {
+        0.5 + 0.5 * listener.local_x().dot(offset / distance)
+    } else {
+        0.5
+    };
+    (attenuation, panning)
+}
+
+fn sync_mixer(settings: Res<Settings>, mut mixer: ResMut<AudioMixer>) {
+    if settings.is_changed() {
+        mixer.master = settings.volume;
+        mixer.effects = settings.effects_volume;
+        mixer.ambience = settings.ambience_volume;
+        mixer.muted = settings.muted;
+    }
+}
+
+fn mute_and_unlock(
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    mut mixer: ResMut<AudioMixer>,
+    mut settings: ResMut<Settings>,
+) {
+    if !mixer.unlocked
+        && (keyboard_input.get_just_pressed().len() > 0 || mouse_input.get_just_pressed().len() > 0)
+    {
+        mixer.unlocked = true;
+    }
+    if keyboard_input.just_pressed(KeyCode::M) {
+        settings.muted = !settings.muted;
+    }
+}
+
+fn interaction_sounds(
+    mut picked_up: EventReader<ItemPickedUp>,
+    mut placed: EventReader<ItemPlaced>,
+    mut removed: EventReader<ItemRemoved>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    for _ in picked_up.iter() {
+        sounds.send(PlaySound::at_listener(SoundEffect::Pickup));
+    }
+    for _ in removed.iter() {
+        sounds.send(PlaySound::at_listener(SoundEffect::Pickup));
+    }
+    for event in placed.iter() {
+        sounds.send(PlaySound::at(SoundEffect::Place, event.entity));
+    }
+}
+
+fn resolve_sounds(
+    mut events: EventReader<PlaySound>,
+    mixer: Res<AudioMixer>,
+    listener: Query<&GlobalTransform, With<FlyCam>>,
+    emitters: Query<&GlobalTransform>,
+    mut pending: ResMut<PendingSounds>,
+) {
+    let volume = mixer.effects_volume();
+    let listener = listener.iter().next();
+    for event in events.iter() {
+        if volume <= 0.0 {
+            continue;
+        }
+        let emitter = event
+            .emitter
+            .and_then(|entity| emitters.get(entity).ok())
+            .map(|transform| transform.translation);
+        let (attenuation, panning) = match (listener, emitter) {
+            (Some(listener), Some(emitter)) => spatialize(listener, emitter),
+            _ => (1.0, 0.5),
+        };
+        pending.0.push(ResolvedSound {
+            effect: event.effect,
+            volume: volume * attenuation,
+            panning,
+        });
+    }
+}
+
+fn update_loop_levels(mixer: Res<AudioMixer>, player: Res<Player>, mut levels: ResMut<LoopLevels>) {
+    levels.ambience = mixer.ambience_volume();
+    levels.blacklight_hum = if player.equipped_name() == "InvBlacklightFlashlight" {
+        0.4 * mixer.effects_volume()
+    } else {
+        0.0
+    };
+}
+
+/// Sound effects positioned at their emitting entity plus looping ambience
+/// and blacklight hum, all routed through the `AudioMixer`.
+#[derive(Default)]
+pub struct GameAudioPlugin {
+    /// keeps `PlaySound` and the mixer but plays nothing, so no audio device
+    /// is opened
+    pub headless: bool,
+}
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<PlaySound>();
+        app.init_resource::<AudioMixer>();
+        app.init_resource::<PendingSounds>();
+        app.init_resource::<LoopLevels>();
+        app.add_system(sync_mixer.system().label("sync_mixer"));
+        app.add_system(mute_and_unlock.system().before("sync_mixer"));
+        app.add_system(interaction_sounds.system());
+        if self.headless {
+            return;
+        }
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            resolve_sounds.system().label(AUDIO_RESOLVE),
+        );
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            update_loop_levels.system().label(AUDIO_RESOLVE),
+        );
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_plugin(native::NativeAudioPlugin);
+        #[cfg(target_arch = "wasm32")]
+        app.add_plugin(web::WebAudioPlugin);
+    }
+}
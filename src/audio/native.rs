@@ -0,0 +1,86 @@
+use super::{
+    LoopLevels, PendingSounds, SoundEffect, SoundLoop, AUDIO_RESOLVE, SOUND_EFFECTS, SOUND_LOOPS,
+};
+use bevy::prelude::*;
+use bevy_kira_audio::{Audio, AudioChannel, AudioPlugin, AudioSource};
+use std::collections::{HashMap, HashSet};
+
+/// kira mixes per channel, so every effect and loop gets its own channel to
+/// allow setting volume and panning independently.
+fn effect_channel(effect: SoundEffect) -> AudioChannel {
+    AudioChannel::new(format!("effect_{:?}", effect))
+}
+
+fn loop_channel(sound_loop: SoundLoop) -> AudioChannel {
+    AudioChannel::new(format!("loop_{:?}", sound_loop))
+}
+
+#[derive(Default)]
+struct SoundHandles {
+    effects: HashMap<SoundEffect, Handle<AudioSource>>,
+    loops: HashMap<SoundLoop, Handle<AudioSource>>,
+}
+
+fn load_sounds(asset_server: Res<AssetServer>, mut handles: ResMut<SoundHandles>) {
+    for effect in SOUND_EFFECTS {
+        handles
+            .effects
+            .insert(*effect, asset_server.load(effect.path()));
+    }
+    for sound_loop in SOUND_LOOPS {
+        handles
+            .loops
+            .insert(*sound_loop, asset_server.load(sound_loop.path()));
+    }
+}
+
+fn play_sounds(audio: Res<Audio>, handles: Res<SoundHandles>, mut pending: ResMut<PendingSounds>) {
+    for sound in pending.0.drain(..) {
+        if let Some(handle) = handles.effects.get(&sound.effect) {
+            let channel = effect_channel(sound.effect);
+            audio.set_volume_in_channel(sound.volume, &channel);
+            audio.set_panning_in_channel(sound.panning, &channel);
+            audio.play_in_channel(handle.clone(), &channel);
+        }
+    }
+}
+
+fn play_loops(
+    audio: Res<Audio>,
+    handles: Res<SoundHandles>,
+    levels: Res<LoopLevels>,
+    mut playing: Local<HashSet<SoundLoop>>,
+) {
+    for sound_loop in SOUND_LOOPS {
+        let level = levels.level(*sound_loop);
+        let channel = loop_channel(*sound_loop);
+        if level > 0.0 {
+            audio.set_volume_in_channel(level, &channel);
+            if playing.insert(*sound_loop) {
+                if let Some(handle) = handles.loops.get(sound_loop) {
+                    audio.play_looped_in_channel(handle.clone(), &channel);
+                }
+            }
+        } else if playing.remove(sound_loop) {
+            audio.stop_channel(&channel);
+        }
+    }
+}
+
+pub struct NativeAudioPlugin;
+
+impl Plugin for NativeAudioPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(AudioPlugin);
+        app.init_resource::<SoundHandles>();
+        app.add_startup_system(load_sounds.system());
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            play_sounds.system().after(AUDIO_RESOLVE),
+        );
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            play_loops.system().after(AUDIO_RESOLVE),
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use super::{LoopLevels, PendingSounds, SoundLoop, AUDIO_RESOLVE, SOUND_LOOPS};
+use bevy::{asset::AssetServerSettings, prelude::*};
+use std::collections::HashMap;
+use web_sys::HtmlAudioElement;
+
+/// Relative to the asset folder, like bevy's web asset io, which is
+/// `assets` next to the page unless the host page set a base URL.
+fn asset_url(folder: &str, path: &str) -> String {
+    format!("{}/{}", folder.trim_end_matches('/'), path)
+}
+
+fn new_element(folder: &str, path: &str) -> Option<HtmlAudioElement> {
+    HtmlAudioElement::new_with_src(&asset_url(folder, path)).ok()
+}
+
+/// `HtmlAudioElement` has no panning, so positioned sounds on the web are
+/// only attenuated by distance.
+fn play_sounds(asset_settings: Res<AssetServerSettings>, mut pending: ResMut<PendingSounds>) {
+    for sound in pending.0.drain(..) {
+        if let Some(element) = new_element(&asset_settings.asset_folder, sound.effect.path()) {
+            element.set_volume(sound.volume.clamp(0.0, 1.0) as f64);
+            // the promise rejects if the page has not been interacted with yet,
+            // which the mixer already guards against
+            let _ = element.play();
+        }
+    }
+}
+
+/// DOM handles are not `Send`, so this lives in a non-send resource.
+#[derive(Default)]
+struct LoopElements(HashMap<SoundLoop, HtmlAudioElement>);
+
+fn play_loops(
+    asset_settings: Res<AssetServerSettings>,
+    levels: Res<LoopLevels>,
+    mut elements: NonSendMut<LoopElements>,
+) {
+    for sound_loop in SOUND_LOOPS {
+        let level = levels.level(*sound_loop);
+        if level > 0.0 {
+            if !elements.0.contains_key(sound_loop) {
+                if let Some(element) = new_element(&asset_settings.asset_folder, sound_loop.path())
+                {
+                    element.set_loop(true);
+                    let _ = element.play();
+                    elements.0.insert(*sound_loop, element);
+                }
+            }
+            if let Some(element) = elements.0.get(sound_loop) {
+                element.set_volume(level.clamp(0.0, 1.0) as f64);
+            }
+        } else if let Some(element) = elements.0.remove(sound_loop) {
+            let _ = element.pause();
+        }
+    }
+}
+
+pub struct WebAudioPlugin;
+
+impl Plugin for WebAudioPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_non_send_resource::<LoopElements>();
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            play_sounds.system().after(AUDIO_RESOLVE),
+        );
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            play_loops.system().after(AUDIO_RESOLVE),
+        );
+    }
+}
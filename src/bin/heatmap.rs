@@ -0,0 +1,185 @@
+//! Renders where players spent their time in a room as a top-down heatmap,
+//! from the path samples the game writes.
+//!
+//!     cargo run --bin heatmap <room> [samples] [asset folder]
+//!
+//! The samples default to `bevy_er_path.csv` and the asset folder to
+//! `assets`. The picture covers the floor of the room's `Room` mesh, with
+//! +x to the right and +z down, and is saved as `<room>_heatmap.png`.
+
+use bevy::math::{Mat4, Vec2, Vec3};
+use bevy_er::{Campaign, EscapeRoomConfig, PathSample};
+use std::{path::Path, process};
+
+/// pixels along the longer side of the floor
+const IMAGE_SIZE: f32 = 512.0;
+
+/// Finds `name` under `node` and returns its mesh bounds in scene space,
+/// as the minimum and maximum corners on the floor.
+fn find_bounds(node: gltf::Node, parent: Mat4, name: &str) -> Option<(Vec2, Vec2)> {
+    let transform = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+    if node.name() == Some(name) {
+        let mut min = Vec2::splat(f32::MAX);
+        let mut max = Vec2::splat(f32::MIN);
+        for primitive in node.mesh()?.primitives() {
+            let bounds = primitive.bounding_box();
+            for corner in 0..8 {
+                let pick = |axis: usize| {
+                    if corner & (1 << axis) == 0 {
+                        bounds.min[axis]
+                    } else {
+                        bounds.max[axis]
+                    }
+                };
+                let point = transform.transform_point3(Vec3::new(pick(0), pick(1), pick(2)));
+                min = min.min(Vec2::new(point.x, point.z));
+                max = max.max(Vec2::new(point.x, point.z));
+            }
+        }
+        return Some((min, max));
+    }
+    node.children()
+        .find_map(|child| find_bounds(child, transform, name))
+}
+
+fn floor_bounds(folder: &Path, scene: &str) -> Result<(Vec2, Vec2), String> {
+    let file = scene.split('#').next().unwrap_or(scene);
+    let index = scene
+        .split('#')
+        .nth(1)
+        .and_then(|label| label.strip_prefix("Scene"))
+        .and_then(|index| index.parse::<usize>().ok())
+        .unwrap_or(0);
+    let document =
+        gltf::Gltf::open(folder.join(file)).map_err(|err| format!("{}: {}", file, err))?;
+    let scene = document
+        .scenes()
+        .nth(index)
+        .ok_or_else(|| format!("{}: there is no scene {}", file, index))?;
+    scene
+        .nodes()
+        .find_map(|node| find_bounds(node, Mat4::IDENTITY, "Room"))
+        .ok_or_else(|| format!("{}: no Room mesh to take the floor from", file))
+}
+
+/// black through red and yellow to white
+fn heat_color(heat: f32) -> image::Rgb<u8> {
+    let channel = |value: f32| (value.max(0.0).min(1.0) * 255.0) as u8;
+    image::Rgb([
+        channel(heat * 3.0),
+        channel(heat * 3.0 - 1.0),
+        channel(heat * 3.0 - 2.0),
+    ])
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let room_id = match args.next() {
+        Some(room_id) => room_id,
+        None => {
+            eprintln!("usage: heatmap <room> [samples] [asset folder]");
+            process::exit(1);
+        }
+    };
+    let samples_path = args
+        .next()
+        .unwrap_or_else(|| "bevy_er_path.csv".to_string());
+    let folder = args.next().unwrap_or_else(|| "assets".to_string());
+    let folder = Path::new(&folder);
+    let config = EscapeRoomConfig::default();
+
+    let campaign = std::fs::read(folder.join(&config.campaign))
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| ron::de::from_bytes::<Campaign>(&bytes).map_err(|err| err.to_string()));
+    let room = campaign.and_then(|campaign| {
+        campaign
+            .rooms
+            .into_iter()
+            .find(|room| room.id == room_id)
+            .ok_or_else(|| format!("no room named {}", room_id))
+    });
+    let bounds = room.and_then(|room| floor_bounds(folder, &room.scene));
+    let (min, max) = match bounds {
+        Ok(bounds) => bounds,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    let samples: Vec<PathSample> = match std::fs::read_to_string(&samples_path) {
+        Ok(text) => text
+            .lines()
+            .filter_map(PathSample::from_csv)
+            .filter(|sample| sample.room == room_id)
+            .collect(),
+        Err(err) => {
+            eprintln!("{}: {}", samples_path, err);
+            process::exit(1);
+        }
+    };
+    if samples.is_empty() {
+        eprintln!("{}: no samples in {}", samples_path, room_id);
+        process::exit(1);
+    }
+
+    let size = max - min;
+    let scale = IMAGE_SIZE / size.x.max(size.y);
+    let width = (size.x * scale).ceil().max(1.0) as u32;
+    let height = (size.y * scale).ceil().max(1.0) as u32;
+    // samples are taken at a fixed rate, so counting them measures time
+    let mut counts = vec![0u32; (width * height) as usize];
+    for sample in samples.iter() {
+        let pixel = (Vec2::new(sample.position.x, sample.position.z) - min) * scale;
+        if pixel.x < 0.0 || pixel.y < 0.0 {
+            continue;
+        }
+        let (x, y) = (pixel.x as u32, pixel.y as u32);
+        if x < width && y < height {
+            counts[(y * width + x) as usize] += 1;
+        }
+    }
+    // spread each sample over a small disc so single visits stay visible
+    let radius = (scale * 0.5).max(1.0) as i64;
+    let mut heat = vec![0f32; counts.len()];
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            let count = counts[(y * width as i64 + x) as usize];
+            if count == 0 {
+                continue;
+            }
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if dx * dx + dy * dy > radius * radius
+                        || nx < 0
+                        || ny < 0
+                        || nx >= width as i64
+                        || ny >= height as i64
+                    {
+                        continue;
+                    }
+                    heat[(ny * width as i64 + nx) as usize] += count as f32;
+                }
+            }
+        }
+    }
+    // logarithmic, so a long wait in one spot doesn't wash out the rest
+    let hottest = heat.iter().cloned().fold(0.0, f32::max).ln_1p();
+    let picture = image::RgbImage::from_fn(width, height, |x, y| {
+        heat_color(heat[(y * width + x) as usize].ln_1p() / hottest)
+    });
+
+    let output = format!("{}_heatmap.png", room_id);
+    if let Err(err) = picture.save(&output) {
+        eprintln!("{}: {}", output, err);
+        process::exit(1);
+    }
+    println!(
+        "{}: {} samples over {:.1} by {:.1}",
+        output,
+        samples.len(),
+        size.x,
+        size.y
+    );
+}
@@ -0,0 +1,56 @@
+//! Plays a recording saved with F6 back without a window and reports how
+//! the puzzle ended up.
+//!
+//!     cargo run --bin replay [recording] [asset folder]
+//!
+//! The recording defaults to `bevy_er_recording.ron` and the asset folder to
+//! `assets`. Frames run as fast as they can; the game clock steps by the
+//! recorded frame deltas, so anything timed or animated moves as far as it did
+//! for the player.
+
+use bevy::{
+    app::{ScheduleRunnerPlugin, ScheduleRunnerSettings},
+    asset::AssetServerSettings,
+    prelude::*,
+    wgpu::WgpuPlugin,
+    winit::WinitPlugin,
+};
+use bevy_egui::EguiPlugin;
+use bevy_er::{EscapeRoomPlugin, Recording, ReplayPlugin};
+use std::{process, time::Duration};
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let path = args
+        .next()
+        .unwrap_or_else(|| "bevy_er_recording.ron".to_string());
+    let asset_folder = args.next().unwrap_or_else(|| "assets".to_string());
+
+    let recording = std::fs::read(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| ron::de::from_bytes::<Recording>(&bytes).map_err(|err| err.to_string()));
+    let recording = match recording {
+        Ok(recording) => recording,
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            process::exit(1);
+        }
+    };
+
+    let mut app = App::build();
+    app.insert_resource(AssetServerSettings { asset_folder });
+    app.insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs(0)));
+    // without winit and wgpu nothing opens a window or renders, but meshes
+    // and scenes still load for picking
+    app.add_plugins_with(DefaultPlugins, |group| {
+        group.disable::<WinitPlugin>().disable::<WgpuPlugin>()
+    });
+    app.add_plugin(ScheduleRunnerPlugin::default());
+    app.add_plugin(EguiPlugin);
+    app.add_plugin(EscapeRoomPlugin {
+        headless: true,
+        ..Default::default()
+    });
+    app.add_plugin(ReplayPlugin { recording });
+    app.run();
+}
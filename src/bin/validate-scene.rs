@@ -0,0 +1,114 @@
+//! Checks the glTF scenes of the campaign against the nodes the game looks
+//! up by name, without starting the game.
+//!
+//!     cargo run --bin validate-scene [asset folder]
+//!
+//! The asset folder defaults to `assets`. Exits with a failure status if any
+//! scene has problems.
+
+use bevy_er::{
+    required_room_nodes, validate_scene, Campaign, EscapeRoomConfig, SceneNode, ValidationError,
+    PLAYER_SCENE_NODES,
+};
+use std::{path::Path, process};
+
+fn collect_nodes(node: gltf::Node, nodes: &mut Vec<SceneNode>) {
+    if let Some(name) = node.name() {
+        nodes.push(SceneNode {
+            name: name.to_string(),
+            has_children: node.mesh().is_some() || node.children().next().is_some(),
+        });
+    }
+    for child in node.children() {
+        collect_nodes(child, nodes);
+    }
+}
+
+/// Reads the nodes of one scene, `path` being an asset path like
+/// `er.gltf#Scene0`.
+fn read_scene(folder: &Path, path: &str) -> Result<Vec<SceneNode>, String> {
+    let mut parts = path.splitn(2, '#');
+    let file = parts.next().unwrap_or(path);
+    let index = match parts.next() {
+        Some(label) => label
+            .strip_prefix("Scene")
+            .and_then(|index| index.parse::<usize>().ok())
+            .ok_or_else(|| format!("{}: {} is not a scene label", path, label))?,
+        None => 0,
+    };
+    let document =
+        gltf::Gltf::open(folder.join(file)).map_err(|err| format!("{}: {}", file, err))?;
+    let scene = document
+        .scenes()
+        .nth(index)
+        .ok_or_else(|| format!("{}: there is no scene {}", file, index))?;
+    let mut nodes = Vec::new();
+    for node in scene.nodes() {
+        collect_nodes(node, &mut nodes);
+    }
+    Ok(nodes)
+}
+
+fn report(scene: &str, errors: &[ValidationError]) {
+    if errors.is_empty() {
+        println!("{}: ok", scene);
+    }
+    for error in errors {
+        eprintln!("{}", error);
+    }
+}
+
+fn main() {
+    let folder = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "assets".to_string());
+    let folder = Path::new(&folder);
+    let config = EscapeRoomConfig::default();
+    let mut failed = false;
+
+    let required: Vec<String> = PLAYER_SCENE_NODES
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+    match read_scene(folder, &config.player_scene) {
+        Ok(nodes) => {
+            let errors = validate_scene(&config.player_scene, &nodes, &required);
+            failed |= !errors.is_empty();
+            report(&config.player_scene, &errors);
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            failed = true;
+        }
+    }
+
+    let campaign = std::fs::read(folder.join(&config.campaign))
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| ron::de::from_bytes::<Campaign>(&bytes).map_err(|err| err.to_string()));
+    let campaign = match campaign {
+        Ok(campaign) => campaign,
+        Err(err) => {
+            eprintln!("{}: {}", config.campaign, err);
+            process::exit(1);
+        }
+    };
+    for room in campaign.rooms.iter() {
+        let scene = format!("room {} ({})", room.id, room.scene);
+        let required = required_room_nodes(&room.puzzle, Some(room));
+        match read_scene(folder, &room.scene) {
+            Ok(nodes) => {
+                let errors = validate_scene(&scene, &nodes, &required);
+                failed |= !errors.is_empty();
+                report(&scene, &errors);
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,318 @@
+//! Running inside a web page. The host page starts the game with `run`,
+//! optionally passing a config object:
+//!
+//! ```text
+//! wasm.run({
+//!     canvas: "#game",                          // CSS selector of the canvas to draw into
+//!     assetBaseUrl: "https://cdn.example.com/er", // instead of `assets` next to the page
+//!     analyticsEndpoint: "/playtest",           // see `EscapeRoomConfig::analytics_endpoint`
+//! });
+//! ```
+//!
+//! Without `canvas` bevy adds a canvas to the body. The canvas is made
+//! focusable and focused so keys reach the game without clicking it first,
+//! and clicking it outside the UI locks the pointer with the Pointer Lock
+//! API. Where the API is missing or the lock is refused the keyboard look
+//! keys remain and a hint says so. The game pauses when the page is hidden or the window loses
+//! focus, and the page can call the `pause()` and `resume()` exports.
+//!
+//! winit turns touches on the web into mouse clicks, so the canvas' touch
+//! events are forwarded as bevy `TouchInput` events for `TouchControls`, and
+//! kept from scrolling or zooming the page.
+
+use crate::{add_game, has_window, pause::Paused, EscapeRoomConfig, Localization, TouchControls};
+use bevy::{
+    asset::AssetServerSettings,
+    input::{
+        touch::{TouchInput, TouchPhase},
+        InputSystem,
+    },
+    prelude::*,
+};
+use bevy_egui::{egui, EguiContext};
+use std::{
+    cell::RefCell,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use wasm_bindgen::{prelude::*, JsCast};
+
+// DOM callbacks can't reach the ECS, so they leave flags for `sync_host`
+static PAUSE_REQUESTED: AtomicBool = AtomicBool::new(false);
+static RESUME_REQUESTED: AtomicBool = AtomicBool::new(false);
+static POINTER_LOCKED: AtomicBool = AtomicBool::new(false);
+static POINTER_LOCK_FAILED: AtomicBool = AtomicBool::new(false);
+static UI_WANTS_POINTER: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// touches from the canvas waiting for `forward_touches`
+    static TOUCHES: RefCell<Vec<TouchInput>> = RefCell::new(Vec::new());
+}
+
+/// The config object passed to `run`, every field is optional.
+#[derive(Debug, Default, Clone)]
+struct HostConfig {
+    canvas: Option<String>,
+    asset_base_url: Option<String>,
+    analytics_endpoint: Option<String>,
+}
+
+impl HostConfig {
+    fn from_js(config: &JsValue) -> Self {
+        let field = |name: &str| {
+            if !config.is_object() {
+                return None;
+            }
+            js_sys::Reflect::get(config, &JsValue::from_str(name))
+                .ok()
+                .and_then(|value| value.as_string())
+        };
+        Self {
+            canvas: field("canvas"),
+            asset_base_url: field("assetBaseUrl"),
+            analytics_endpoint: field("analyticsEndpoint"),
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub fn run(config: JsValue) {
+    let host = HostConfig::from_js(&config);
+    let mut app = App::build();
+    if let Some(asset_folder) = host.asset_base_url.clone() {
+        app.insert_resource(AssetServerSettings { asset_folder });
+    }
+    app.insert_resource(WindowDescriptor {
+        canvas: host.canvas.clone(),
+        ..Default::default()
+    });
+    add_game(
+        &mut app,
+        EscapeRoomConfig {
+            analytics_endpoint: host.analytics_endpoint.clone(),
+            ..Default::default()
+        },
+    );
+    app.insert_resource(host);
+    app.add_plugin(BrowserPlugin);
+    app.run();
+}
+
+#[wasm_bindgen]
+pub fn pause() {
+    PAUSE_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+#[wasm_bindgen]
+pub fn resume() {
+    RESUME_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+fn find_canvas(document: &web_sys::Document, selector: &str) -> Option<web_sys::HtmlCanvasElement> {
+    document.query_selector(selector).ok()??.dyn_into().ok()
+}
+
+/// Calls `callback` on every `event` for as long as the page lives.
+fn listen(target: &web_sys::EventTarget, event: &str, callback: impl FnMut() + 'static) {
+    let closure = Closure::wrap(Box::new(callback) as Box<dyn FnMut()>);
+    if let Err(err) =
+        target.add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+    {
+        error!("unable to listen for {}: {:?}", event, err);
+    }
+    closure.forget();
+}
+
+/// Like `listen`, for callbacks that need the event itself.
+fn listen_with_event<E: JsCast + 'static>(
+    target: &web_sys::EventTarget,
+    event: &str,
+    mut callback: impl FnMut(E) + 'static,
+) {
+    let forward = move |event: JsValue| callback(event.unchecked_into());
+    let closure = Closure::wrap(Box::new(forward) as Box<dyn FnMut(JsValue)>);
+    if let Err(err) =
+        target.add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+    {
+        error!("unable to listen for {}: {:?}", event, err);
+    }
+    closure.forget();
+}
+
+/// Queues the touches that changed in `event`, in logical pixels from the
+/// canvas' top left as `TouchControls` expects.
+fn queue_touches(
+    canvas: &web_sys::HtmlCanvasElement,
+    event: &web_sys::TouchEvent,
+    phase: TouchPhase,
+) {
+    // no scrolling, zooming or emulated mouse events
+    event.prevent_default();
+    let rect = canvas.get_bounding_client_rect();
+    let changed = event.changed_touches();
+    TOUCHES.with(|touches| {
+        let mut touches = touches.borrow_mut();
+        for touch in (0..changed.length()).filter_map(|index| changed.get(index)) {
+            let x = touch.client_x() as f64 - rect.left();
+            let y = touch.client_y() as f64 - rect.top();
+            touches.push(TouchInput {
+                phase,
+                position: Vec2::new(x as f32, y as f32),
+                force: None,
+                id: touch.identifier() as u64,
+            });
+        }
+    });
+}
+
+/// Runs until bevy has created the canvas, then hooks up focus, pointer
+/// lock, pausing and touches.
+fn attach_to_page(host: Res<HostConfig>, mut attached: Local<bool>) {
+    if *attached {
+        return;
+    }
+    let window = match web_sys::window() {
+        Some(window) => window,
+        None => return,
+    };
+    let document = match window.document() {
+        Some(document) => document,
+        None => return,
+    };
+    let canvas = match find_canvas(&document, host.canvas.as_deref().unwrap_or("canvas")) {
+        Some(canvas) => canvas,
+        None => return,
+    };
+    *attached = true;
+
+    // a canvas only takes keyboard focus with a tabindex
+    if let Err(err) = canvas.set_attribute("tabindex", "0") {
+        error!("unable to make the canvas focusable: {:?}", err);
+    }
+    let _ = canvas.focus();
+
+    // Safari on iOS has no pointer lock at all
+    let supported =
+        js_sys::Reflect::has(&canvas, &JsValue::from_str("requestPointerLock")).unwrap_or(false);
+    if !supported {
+        POINTER_LOCK_FAILED.store(true, Ordering::Relaxed);
+    }
+    {
+        let clicked = canvas.clone();
+        listen(&canvas, "click", move || {
+            let _ = clicked.focus();
+            // clicks on menus, sliders and the console keep the cursor
+            if supported
+                && !POINTER_LOCKED.load(Ordering::Relaxed)
+                && !UI_WANTS_POINTER.load(Ordering::Relaxed)
+            {
+                clicked.request_pointer_lock();
+            }
+        });
+    }
+    {
+        let locked = document.clone();
+        listen(&document, "pointerlockchange", move || {
+            let is_locked = locked.pointer_lock_element().is_some();
+            POINTER_LOCKED.store(is_locked, Ordering::Relaxed);
+            if is_locked {
+                POINTER_LOCK_FAILED.store(false, Ordering::Relaxed);
+            }
+        });
+    }
+    listen(&document, "pointerlockerror", || {
+        POINTER_LOCK_FAILED.store(true, Ordering::Relaxed);
+    });
+    {
+        let hidden = document.clone();
+        listen(&document, "visibilitychange", move || {
+            if hidden.hidden() {
+                PAUSE_REQUESTED.store(true, Ordering::Relaxed);
+            }
+        });
+    }
+    listen(&window, "blur", || {
+        PAUSE_REQUESTED.store(true, Ordering::Relaxed);
+    });
+    let phases = [
+        ("touchstart", TouchPhase::Started),
+        ("touchmove", TouchPhase::Moved),
+        ("touchend", TouchPhase::Ended),
+        ("touchcancel", TouchPhase::Cancelled),
+    ];
+    for (event, phase) in phases.iter().copied() {
+        let touched = canvas.clone();
+        listen_with_event(&canvas, event, move |event: web_sys::TouchEvent| {
+            queue_touches(&touched, &event, phase);
+        });
+    }
+}
+
+/// Sends the touches the canvas queued, ahead of bevy's `Touches` update.
+fn forward_touches(mut touch_events: EventWriter<TouchInput>) {
+    TOUCHES.with(|touches| touch_events.send_batch(touches.borrow_mut().drain(..)));
+}
+
+/// Applies `pause()` and `resume()` from the page, and lets go of the
+/// pointer while paused so the Resume button can be clicked.
+fn sync_host(mut paused: ResMut<Paused>) {
+    if PAUSE_REQUESTED.swap(false, Ordering::Relaxed) {
+        paused.0 = true;
+    }
+    if RESUME_REQUESTED.swap(false, Ordering::Relaxed) {
+        paused.0 = false;
+    }
+    if paused.is_changed() && paused.0 && POINTER_LOCKED.load(Ordering::Relaxed) {
+        if let Some(document) = web_sys::window().and_then(|window| window.document()) {
+            document.exit_pointer_lock();
+        }
+    }
+}
+
+/// Tells the click listener whether the pointer is over egui.
+fn track_ui_pointer(egui_context: Res<EguiContext>) {
+    let ctx = egui_context.ctx();
+    let wants_pointer = ctx.wants_pointer_input() || ctx.is_pointer_over_area();
+    UI_WANTS_POINTER.store(wants_pointer, Ordering::Relaxed);
+}
+
+fn pointer_lock_hint(
+    egui_context: Res<EguiContext>,
+    localization: Res<Localization>,
+    touch: Res<TouchControls>,
+) {
+    // phones have the touch sticks instead
+    if !POINTER_LOCK_FAILED.load(Ordering::Relaxed) || touch.enabled {
+        return;
+    }
+    egui::Window::new("pointer_lock_hint")
+        .title_bar(false)
+        .resizable(false)
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+        .show(egui_context.ctx(), |ui| {
+            ui.label(localization.tr("pointer-lock-unavailable"));
+        });
+}
+
+/// Focus, pointer lock, pausing and touches for the page hosting the game.
+struct BrowserPlugin;
+
+impl Plugin for BrowserPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(attach_to_page.system());
+        app.add_system_to_stage(
+            CoreStage::PreUpdate,
+            forward_touches.system().before(InputSystem),
+        );
+        app.add_system(sync_host.system().before("pause"));
+        app.add_system(
+            track_ui_pointer
+                .system()
+                .with_run_criteria(has_window.system()),
+        );
+        app.add_system(
+            pointer_lock_hint
+                .system()
+                .with_run_criteria(has_window.system()),
+        );
+    }
+}
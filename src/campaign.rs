@@ -0,0 +1,260 @@
+//! The campaign manifest lists the rooms in play order, the scene and puzzle
+//! of each one and which rooms have to be completed before another unlocks.
+//! Completed rooms are remembered between sessions and can be replayed from
+//! the main menu.
+
+use crate::{
+    achievements::Achievement,
+    cutscene::CutscenePlayer,
+    has_window,
+    input::InputBlock,
+    loading::LoadingAssets,
+    localization::Localization,
+    rooms::{CurrentRoom, EnterRoom, RoomDefinition, Rooms},
+    storage::{self, StorageConfig},
+    EscapeRoomConfig, PuzzleState,
+};
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use bevy_egui::{egui, EguiContext};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+pub const CAMPAIGN_PATH: &str = "escape_room.campaign";
+const PROGRESS_KEY: &str = "bevy_er_progress";
+
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "2e5e53d9-247a-46fa-8622-cd8459bd0098"]
+pub struct Campaign {
+    pub title: String,
+    /// id of the room a new game starts in
+    pub start: String,
+    pub rooms: Vec<RoomDefinition>,
+    #[serde(default)]
+    pub achievements: Vec<Achievement>,
+}
+
+#[derive(Default)]
+pub struct CampaignLoader;
+
+impl AssetLoader for CampaignLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let campaign: Campaign = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(campaign));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["campaign"]
+    }
+}
+
+/// Ids of the rooms the player has solved, persisted between sessions.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CampaignProgress {
+    pub completed: HashSet<String>,
+}
+
+impl CampaignProgress {
+    pub fn load() -> Self {
+        storage::load(PROGRESS_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self, storage_config: &StorageConfig) {
+        storage::save(storage_config, PROGRESS_KEY, self);
+    }
+
+    pub fn is_completed(&self, id: &str) -> bool {
+        self.completed.contains(id)
+    }
+}
+
+pub struct CampaignHandle(pub Handle<Campaign>);
+
+pub struct MainMenu {
+    pub open: bool,
+}
+
+impl Default for MainMenu {
+    fn default() -> Self {
+        Self { open: true }
+    }
+}
+
+fn load_campaign(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<EscapeRoomConfig>,
+    mut loading: ResMut<LoadingAssets>,
+) {
+    let handle: Handle<Campaign> = asset_server.load(config.campaign.as_str());
+    loading.add(&config.campaign, handle.clone_untyped());
+    commands.insert_resource(CampaignHandle(handle));
+}
+
+/// Copies the room list into `Rooms` whenever the manifest (re)loads, and
+/// starts loading the room scenes behind the loading screen.
+fn apply_campaign(
+    mut asset_events: EventReader<AssetEvent<Campaign>>,
+    asset_server: Res<AssetServer>,
+    campaigns: Res<Assets<Campaign>>,
+    mut rooms: ResMut<Rooms>,
+    mut loading: ResMut<LoadingAssets>,
+) {
+    for event in asset_events.iter() {
+        let handle = match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => handle,
+            AssetEvent::Removed { .. } => continue,
+        };
+        if let Some(campaign) = campaigns.get(handle) {
+            rooms.definitions = campaign.rooms.clone();
+            rooms.start = campaign.start.clone();
+            for room in campaign.rooms.iter() {
+                let scene: Handle<Scene> = asset_server.load(room.scene.as_str());
+                loading.add(&room.scene, scene.clone_untyped());
+            }
+        }
+    }
+}
+
+fn record_completion(
+    storage_config: Res<StorageConfig>,
+    puzzle_state: Res<PuzzleState>,
+    current: Res<CurrentRoom>,
+    mut progress: ResMut<CampaignProgress>,
+) {
+    if !puzzle_state.0 {
+        return;
+    }
+    if let Some(id) = current.id.as_ref() {
+        if !progress.is_completed(id) {
+            progress.completed.insert(id.clone());
+            progress.save(&storage_config);
+        }
+    }
+}
+
+fn toggle_main_menu(
+    keyboard_input: Res<Input<KeyCode>>,
+    current: Res<CurrentRoom>,
+    cutscene_player: Res<CutscenePlayer>,
+    mut menu: ResMut<MainMenu>,
+) {
+    // Escape skips cutscenes, and there is nothing to return to before a
+    // room has been entered
+    if keyboard_input.just_pressed(KeyCode::Escape)
+        && !cutscene_player.is_playing()
+        && current.id.is_some()
+    {
+        menu.open = !menu.open;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn main_menu(
+    egui_context: Res<EguiContext>,
+    handle: Res<CampaignHandle>,
+    campaigns: Res<Assets<Campaign>>,
+    rooms: Res<Rooms>,
+    progress: Res<CampaignProgress>,
+    current: Res<CurrentRoom>,
+    mut menu: ResMut<MainMenu>,
+    mut input_block: ResMut<InputBlock>,
+    mut enter_room: EventWriter<EnterRoom>,
+    loading: Res<LoadingAssets>,
+    localization: Res<Localization>,
+) {
+    input_block.set("menu", menu.open);
+    if !menu.open || !loading.is_done() {
+        return;
+    }
+
+    let title = campaigns
+        .get(&handle.0)
+        .map(|campaign| campaign.title.as_str())
+        .unwrap_or("Escape Room");
+    let title = localization.tr_or("campaign-title", title);
+    let mut entered = None;
+    egui::Window::new(title)
+        .id(egui::Id::new("main_menu"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(egui_context.ctx(), |ui| {
+            if current.id.is_some() {
+                if ui.button(localization.tr("menu-resume")).clicked() {
+                    menu.open = false;
+                }
+            } else if rooms.get(&rooms.start).is_some()
+                && ui.button(localization.tr("menu-new-game")).clicked()
+            {
+                entered = Some((rooms.start.clone(), false));
+            }
+            ui.separator();
+            egui::Grid::new("rooms").show(ui, |ui| {
+                for room in rooms.definitions.iter() {
+                    ui.label(localization.tr_or(&format!("room-{}", room.id), &room.title));
+                    if progress.is_completed(&room.id) {
+                        ui.label(localization.tr("menu-completed"));
+                        if ui.button(localization.tr("menu-replay")).clicked() {
+                            entered = Some((room.id.clone(), true));
+                        }
+                    } else if rooms.is_unlocked(&room.id, &progress) {
+                        ui.label("");
+                        if ui.button(localization.tr("menu-play")).clicked() {
+                            entered = Some((room.id.clone(), false));
+                        }
+                    } else {
+                        ui.label(localization.tr("menu-locked"));
+                        ui.label("");
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+
+    if let Some((room, reset)) = entered {
+        enter_room.send(EnterRoom {
+            room,
+            arrive_at: None,
+            reset,
+        });
+        menu.open = false;
+    }
+}
+
+/// Loads the campaign manifest and shows the main menu, which starts a new
+/// game in the campaign's `start` room, resumes and replays rooms. Escape
+/// opens the menu again once in a room.
+pub struct CampaignPlugin;
+
+impl Plugin for CampaignPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<Campaign>();
+        app.init_asset_loader::<CampaignLoader>();
+        app.insert_resource(CampaignProgress::load());
+        app.init_resource::<StorageConfig>();
+        app.init_resource::<MainMenu>();
+        app.add_startup_system(load_campaign.system());
+        app.add_system(apply_campaign.system());
+        app.add_system(record_completion.system());
+        app.add_system(toggle_main_menu.system().label("toggle_main_menu"));
+        app.add_system(
+            main_menu
+                .system()
+                .with_run_criteria(has_window.system())
+                .after("toggle_main_menu"),
+        );
+    }
+}
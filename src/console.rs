@@ -0,0 +1,286 @@
+//! Developer console. The backtick key opens a command line for skipping
+//! ahead while testing a room:
+//!
+//!     give InvBlacklightFlashlight
+//!     place Left Blue
+//!     solve
+//!     open_door inside 45
+//!     tp 0 4 0
+//!     reset
+//!
+//! Commands go out as `ConsoleCommand` events and `run_console_commands`
+//! applies them to the same resources normal play changes. While the console
+//! is open the keys typed into it don't reach the game or its hotkeys.
+
+use crate::{
+    has_window,
+    input::InputBlock,
+    open_door,
+    rooms::{CurrentRoom, EnterRoom, Rooms},
+    InsideDoor, InsideDoorAngle, InsideDoorSwung, Location, Player, PuzzleAttempt,
+    PuzzleDefinition, StatueColor, StatueHolders, ITEMS,
+};
+use bevy::{input::InputSystem, prelude::*};
+use bevy_egui::{egui, EguiContext};
+use bevy_flycam::FlyCam;
+use serde::{Deserialize, Serialize};
+
+/// how many lines of output the console keeps
+const HISTORY_LINES: usize = 100;
+
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    input: String,
+    history: Vec<String>,
+}
+
+impl Console {
+    fn print(&mut self, line: String) {
+        self.history.push(line);
+        if self.history.len() > HISTORY_LINES {
+            self.history.remove(0);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ConsoleCommand {
+    Give(String),
+    Place(Location, StatueColor),
+    Solve,
+    /// angle in degrees, 0 is closed
+    OpenDoor(f32),
+    /// position in the current room's coordinates
+    Teleport(Vec3),
+    Reset,
+}
+
+fn parse_location(word: &str) -> Result<Location, String> {
+    match word.to_lowercase().as_str() {
+        "left" => Ok(Location::Left),
+        "middle" | "mid" => Ok(Location::Middle),
+        "right" => Ok(Location::Right),
+        _ => Err(format!("{} is not Left, Middle or Right", word)),
+    }
+}
+
+fn parse_color(word: &str) -> Result<StatueColor, String> {
+    match word.to_lowercase().as_str() {
+        "red" => Ok(StatueColor::Red),
+        "green" => Ok(StatueColor::Green),
+        "blue" => Ok(StatueColor::Blue),
+        _ => Err(format!("{} is not Red, Green or Blue", word)),
+    }
+}
+
+fn parse_number(word: &str) -> Result<f32, String> {
+    word.parse()
+        .map_err(|_| format!("{} is not a number", word))
+}
+
+fn parse_command(line: &str) -> Result<ConsoleCommand, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        ["give", item] => {
+            if ITEMS.iter().any(|name| !name.is_empty() && name == item) {
+                Ok(ConsoleCommand::Give(item.to_string()))
+            } else {
+                Err(format!(
+                    "no item named {}, try one of {}",
+                    item,
+                    ITEMS[..ITEMS.len() - 1].join(", ")
+                ))
+            }
+        }
+        ["place", location, color] => Ok(ConsoleCommand::Place(
+            parse_location(location)?,
+            parse_color(color)?,
+        )),
+        ["solve"] => Ok(ConsoleCommand::Solve),
+        ["open_door", "inside", angle] => Ok(ConsoleCommand::OpenDoor(parse_number(angle)?)),
+        ["open_door", door, _] => Err(format!("no door named {}, only inside opens", door)),
+        ["tp", x, y, z] => Ok(ConsoleCommand::Teleport(Vec3::new(
+            parse_number(x)?,
+            parse_number(y)?,
+            parse_number(z)?,
+        ))),
+        ["reset"] => Ok(ConsoleCommand::Reset),
+        [] => Err("type a command".to_string()),
+        _ => Err(format!(
+            "don't know {}, try give, place, solve, open_door, tp or reset",
+            line.trim()
+        )),
+    }
+}
+
+fn toggle_console(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut console: ResMut<Console>,
+    mut input_block: ResMut<InputBlock>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Grave) {
+        console.open = !console.open;
+    }
+    input_block.set("console", console.open);
+}
+
+/// Drops held and just pressed keys while the console is open, except the
+/// backtick that closes it. `Input` has no `clear` in this bevy, so held keys
+/// are reset one by one.
+fn swallow_typed_keys(console: Res<Console>, mut keys: ResMut<Input<KeyCode>>) {
+    if !console.open {
+        return;
+    }
+    let closing = keys.just_pressed(KeyCode::Grave);
+    let held: Vec<KeyCode> = keys.get_pressed().copied().collect();
+    for key in held {
+        keys.reset(key);
+    }
+    keys.update();
+    if closing {
+        keys.press(KeyCode::Grave);
+    }
+}
+
+fn console_ui(
+    egui_context: Res<EguiContext>,
+    mut console: ResMut<Console>,
+    mut commands: EventWriter<ConsoleCommand>,
+) {
+    if !console.open {
+        return;
+    }
+
+    let console = &mut *console;
+    let mut submitted = None;
+    egui::Window::new("Console")
+        .open(&mut console.open)
+        .default_width(400.0)
+        .show(egui_context.ctx(), |ui| {
+            egui::ScrollArea::from_max_height(200.0).show(ui, |ui| {
+                for line in console.history.iter() {
+                    ui.monospace(line.as_str());
+                }
+            });
+            let response = ui.add(egui::TextEdit::singleline(&mut console.input).code_editor());
+            // the key that opens and closes the console lands in the field too
+            console.input.retain(|c| c != '`');
+            if response.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+                submitted = Some(std::mem::take(&mut console.input));
+            }
+            response.request_focus();
+        });
+
+    if let Some(line) = submitted {
+        console.print(format!("> {}", line));
+        match parse_command(&line) {
+            Ok(command) => commands.send(command),
+            Err(err) => console.print(err),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_console_commands(
+    mut commands: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    mut player: ResMut<Player>,
+    mut statue_holders: ResMut<StatueHolders>,
+    puzzle: Res<PuzzleDefinition>,
+    mut door_angle: ResMut<InsideDoorAngle>,
+    mut door_swung: ResMut<InsideDoorSwung>,
+    mut doors: Query<(&mut InsideDoor, &mut Transform)>,
+    mut camera: Query<&mut Transform, (With<FlyCam>, Without<InsideDoor>)>,
+    rooms: Res<Rooms>,
+    current: Res<CurrentRoom>,
+    mut attempts: EventWriter<PuzzleAttempt>,
+    mut enter: EventWriter<EnterRoom>,
+) {
+    for command in commands.iter() {
+        match command {
+            ConsoleCommand::Give(item) => {
+                player.inventory.insert(item.clone());
+                player.equipped = Player::item_index(item);
+                console.print(format!("gave {}", item));
+            }
+            ConsoleCommand::Place(location, color) => {
+                statue_holders.held_statues.insert(*location, *color);
+                if statue_holders.all_filled() {
+                    attempts.send(PuzzleAttempt {
+                        correct: statue_holders.solved(&puzzle),
+                    });
+                }
+                console.print(format!("placed {:?} on {:?}", color, location));
+            }
+            ConsoleCommand::Solve => {
+                // check_for_solution takes it from here, door and all
+                statue_holders.held_statues = puzzle.solution.clone();
+                attempts.send(PuzzleAttempt { correct: true });
+                console.print("solved".to_string());
+            }
+            ConsoleCommand::OpenDoor(angle) => {
+                // open as far as passing through and the swing are concerned,
+                // which then leaves the door at this angle
+                let open = *angle > 0.0;
+                door_angle.0 = *angle;
+                door_swung.0 = Some(open);
+                for (mut door, mut transform) in doors.iter_mut() {
+                    door.0 = open;
+                    open_door(&mut transform, *angle);
+                }
+                console.print(format!("inside door at {} degrees", angle));
+            }
+            ConsoleCommand::Teleport(position) => {
+                let offset = current
+                    .id
+                    .as_ref()
+                    .and_then(|id| rooms.get(id))
+                    .map(|room| room.offset)
+                    .unwrap_or_default();
+                for mut transform in camera.iter_mut() {
+                    transform.translation = offset + *position;
+                }
+                console.print(format!("moved to {}", position));
+            }
+            ConsoleCommand::Reset => match current.id.as_ref() {
+                Some(room) => {
+                    enter.send(EnterRoom {
+                        room: room.clone(),
+                        arrive_at: None,
+                        reset: true,
+                    });
+                    console.print(format!("reset {}", room));
+                }
+                None => console.print("not in a room".to_string()),
+            },
+        }
+    }
+}
+
+/// The backtick developer console.
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<ConsoleCommand>();
+        app.init_resource::<Console>();
+        app.add_system_to_stage(
+            CoreStage::PreUpdate,
+            swallow_typed_keys
+                .system()
+                .label("swallow_typed_keys")
+                .after(InputSystem)
+                .after("touch_input"),
+        );
+        app.add_system(toggle_console.system().label("toggle_console"));
+        app.add_system(
+            console_ui
+                .system()
+                .with_run_criteria(has_window.system())
+                .label("console_ui")
+                .after("toggle_console"),
+        );
+        app.add_system(run_console_commands.system().after("console_ui"));
+    }
+}
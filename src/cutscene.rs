@@ -0,0 +1,417 @@
+//! Camera cutscenes. The `Camera.NNN` nodes exported in `er.gltf` carry no
+//! camera data, but their transforms make good waypoints, so a cutscene is a
+//! timeline of shots that the player camera is interpolated between. Shots
+//! refer to nodes of the current room's scene by name, so they follow the
+//! scene when it is edited. With `Settings::reduced_motion` the camera cuts
+//! from shot to shot through a fade instead of moving between them.
+
+use crate::{
+    game_time::GameTime,
+    has_window,
+    input::InputBlock,
+    pause::not_paused,
+    rooms::{CurrentRoom, RoomRoot},
+    scene_ready::{SceneKind, SceneReady},
+    settings::Settings,
+    PuzzleState, Rooms,
+};
+use bevy::{prelude::*, transform::TransformSystem};
+use bevy_egui::{egui, EguiContext};
+use bevy_flycam::FlyCam;
+use std::{collections::HashMap, f32::consts::FRAC_PI_2};
+
+pub const INTRO_CUTSCENE: &str = "intro";
+pub const DOOR_CUTSCENE: &str = "door_open";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shot {
+    /// a node from the room scene, usually one of the `Camera.NNN` nodes
+    Node(String),
+    /// from one node towards another, both by name
+    LookAt { eye: String, target: String },
+    /// where the player was standing when the cutscene started
+    Player,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keyframe {
+    pub shot: Shot,
+    /// seconds from the start of the cutscene
+    pub at: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cutscene {
+    pub keyframes: Vec<Keyframe>,
+    pub fade_seconds: f32,
+}
+
+impl Cutscene {
+    fn duration(&self) -> f32 {
+        self.keyframes.last().map(|key| key.at).unwrap_or(0.0)
+    }
+}
+
+pub struct Cutscenes(pub HashMap<String, Cutscene>);
+
+impl Default for Cutscenes {
+    fn default() -> Self {
+        let mut cutscenes = HashMap::new();
+        cutscenes.insert(
+            INTRO_CUTSCENE.to_string(),
+            Cutscene {
+                keyframes: vec![
+                    Keyframe {
+                        shot: Shot::Node("Camera.004".to_string()),
+                        at: 0.0,
+                    },
+                    Keyframe {
+                        shot: Shot::Node("Camera.002".to_string()),
+                        at: 4.0,
+                    },
+                    Keyframe {
+                        shot: Shot::LookAt {
+                            eye: "Camera.002".to_string(),
+                            target: "InsideDoor".to_string(),
+                        },
+                        at: 7.0,
+                    },
+                    Keyframe {
+                        shot: Shot::Player,
+                        at: 10.0,
+                    },
+                ],
+                fade_seconds: 1.0,
+            },
+        );
+        cutscenes.insert(
+            DOOR_CUTSCENE.to_string(),
+            Cutscene {
+                keyframes: vec![
+                    Keyframe {
+                        shot: Shot::Player,
+                        at: 0.0,
+                    },
+                    Keyframe {
+                        shot: Shot::LookAt {
+                            eye: "Camera.002".to_string(),
+                            target: "InsideDoor".to_string(),
+                        },
+                        at: 1.5,
+                    },
+                    Keyframe {
+                        shot: Shot::LookAt {
+                            eye: "Camera.002".to_string(),
+                            target: "InsideDoor".to_string(),
+                        },
+                        at: 4.0,
+                    },
+                    Keyframe {
+                        shot: Shot::Player,
+                        at: 5.0,
+                    },
+                ],
+                fade_seconds: 0.5,
+            },
+        );
+        Self(cutscenes)
+    }
+}
+
+/// Poses of the named nodes of the current room that shots can refer to,
+/// relative to the room root. Collected when the room's scene is ready.
+#[derive(Default)]
+pub struct CutsceneWaypoints(pub HashMap<String, Transform>);
+
+pub struct PlayCutscene(pub String);
+
+struct Playback {
+    cutscene: Cutscene,
+    elapsed: f32,
+    player_pose: Transform,
+    /// hold each shot and cut to the next
+    reduced_motion: bool,
+}
+
+#[derive(Default)]
+pub struct CutscenePlayer {
+    playback: Option<Playback>,
+    /// fade back in after the cutscene has handed control back
+    fade_out: Option<Timer>,
+}
+
+impl CutscenePlayer {
+    /// Player input is ignored while this is true.
+    pub fn is_playing(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    fn fade_alpha(&self) -> f32 {
+        if let Some(playback) = self.playback.as_ref() {
+            let fade = playback.cutscene.fade_seconds.max(f32::EPSILON);
+            let remaining = playback.cutscene.duration() - playback.elapsed;
+            let mut alpha = (1.0 - playback.elapsed / fade).max(1.0 - remaining / fade);
+            if playback.reduced_motion {
+                for cut in playback.cutscene.keyframes.windows(2) {
+                    if cut[0].shot != cut[1].shot {
+                        alpha = alpha.max(1.0 - (playback.elapsed - cut[1].at).abs() / fade);
+                    }
+                }
+            }
+            alpha.clamp(0.0, 1.0)
+        } else if let Some(timer) = self.fade_out.as_ref() {
+            timer.percent_left()
+        } else {
+            0.0
+        }
+    }
+}
+
+/// `entity`'s transform relative to the room root it was spawned under, what
+/// its `GlobalTransform` will be less the room offset, available before
+/// transforms have propagated.
+fn relative_to_room(
+    entity: Entity,
+    nodes: &Query<(&Transform, Option<&Parent>), Without<RoomRoot>>,
+) -> Transform {
+    let mut pose = Transform::identity();
+    let mut next = Some(entity);
+    while let Some(entity) = next {
+        match nodes.get(entity) {
+            Ok((transform, parent)) => {
+                pose = transform.mul_transform(pose);
+                next = parent.map(|parent| parent.0);
+            }
+            Err(_) => break,
+        }
+    }
+    pose
+}
+
+fn collect_waypoints(
+    mut scenes_ready: EventReader<SceneReady>,
+    scene_spawner: Res<SceneSpawner>,
+    names: Query<&Name>,
+    nodes: Query<(&Transform, Option<&Parent>), Without<RoomRoot>>,
+    mut waypoints: ResMut<CutsceneWaypoints>,
+) {
+    for ready in scenes_ready.iter() {
+        if !matches!(ready.kind, SceneKind::Room(_)) {
+            continue;
+        }
+        let instance_entities = match scene_spawner.iter_instance_entities(ready.instance) {
+            Some(instance_entities) => instance_entities,
+            None => continue,
+        };
+        waypoints.0.clear();
+        for entity in instance_entities {
+            let name = match names.get(entity) {
+                Ok(name) => name,
+                Err(_) => continue,
+            };
+            let mut pose = relative_to_room(entity, &nodes);
+            // without camera data nothing applies the glTF camera convention,
+            // which looks down -Z after a quarter turn about X
+            if name.as_str().starts_with("Camera") {
+                pose.rotation *= Quat::from_rotation_x(-FRAC_PI_2);
+            }
+            waypoints.0.insert(name.to_string(), pose);
+        }
+    }
+}
+
+/// The door cutscene follows `PuzzleState`, so solves from scripts or the
+/// console play it too.
+fn trigger_cutscenes(
+    waypoints: Res<CutsceneWaypoints>,
+    current: Res<CurrentRoom>,
+    puzzle_state: Res<PuzzleState>,
+    mut intro_played: Local<bool>,
+    mut last_state: Local<(Option<String>, bool)>,
+    mut events: EventWriter<PlayCutscene>,
+) {
+    if !*intro_played && waypoints.0.contains_key("Camera.004") {
+        *intro_played = true;
+        events.send(PlayCutscene(INTRO_CUTSCENE.to_string()));
+    }
+    // entering a room that was solved before isn't a solve
+    let state = (current.id.clone(), puzzle_state.0);
+    if puzzle_state.is_changed() && state.1 && !last_state.1 && state.0 == last_state.0 {
+        events.send(PlayCutscene(DOOR_CUTSCENE.to_string()));
+    }
+    *last_state = state;
+}
+
+fn start_cutscene(
+    mut events: EventReader<PlayCutscene>,
+    cutscenes: Res<Cutscenes>,
+    settings: Res<Settings>,
+    mut cutscene_player: ResMut<CutscenePlayer>,
+    mut input_block: ResMut<InputBlock>,
+    camera: Query<&Transform, With<FlyCam>>,
+) {
+    for PlayCutscene(name) in events.iter() {
+        let cutscene = match cutscenes.0.get(name) {
+            Some(cutscene) => cutscene.clone(),
+            None => {
+                warn!("no cutscene named {}", name);
+                continue;
+            }
+        };
+        // a cutscene interrupting another one returns to the same place
+        let player_pose = match cutscene_player.playback.as_ref() {
+            Some(playback) => playback.player_pose,
+            None => match camera.single() {
+                Ok(transform) => *transform,
+                Err(_) => continue,
+            },
+        };
+        input_block.set("cutscene", true);
+        cutscene_player.fade_out = None;
+        cutscene_player.playback = Some(Playback {
+            cutscene,
+            elapsed: 0.0,
+            player_pose,
+            reduced_motion: settings.reduced_motion,
+        });
+    }
+}
+
+fn resolve_shot(
+    shot: &Shot,
+    waypoints: &CutsceneWaypoints,
+    room_offset: Vec3,
+    player_pose: &Transform,
+) -> Transform {
+    match shot {
+        Shot::Node(name) => match waypoints.0.get(name) {
+            Some(waypoint) => Transform {
+                translation: waypoint.translation + room_offset,
+                rotation: waypoint.rotation,
+                scale: Vec3::ONE,
+            },
+            None => *player_pose,
+        },
+        Shot::LookAt { eye, target } => match (waypoints.0.get(eye), waypoints.0.get(target)) {
+            (Some(eye), Some(target)) => Transform::from_translation(eye.translation + room_offset)
+                .looking_at(target.translation + room_offset, Vec3::Y),
+            _ => *player_pose,
+        },
+        Shot::Player => *player_pose,
+    }
+}
+
+fn sample(playback: &Playback, waypoints: &CutsceneWaypoints, room_offset: Vec3) -> Transform {
+    let keyframes = &playback.cutscene.keyframes;
+    let pose =
+        |key: &Keyframe| resolve_shot(&key.shot, waypoints, room_offset, &playback.player_pose);
+    let next = keyframes
+        .iter()
+        .position(|key| key.at > playback.elapsed)
+        .unwrap_or_else(|| keyframes.len().saturating_sub(1));
+    if next == 0 {
+        return keyframes.first().map(pose).unwrap_or(playback.player_pose);
+    }
+    let (from, to) = (&keyframes[next - 1], &keyframes[next]);
+    if playback.reduced_motion {
+        return pose(from);
+    }
+    let span = (to.at - from.at).max(f32::EPSILON);
+    let s = ((playback.elapsed - from.at) / span).clamp(0.0, 1.0);
+    let s = s * s * (3.0 - 2.0 * s);
+    let (from, to) = (pose(from), pose(to));
+    Transform {
+        translation: from.translation.lerp(to.translation, s),
+        rotation: from.rotation.slerp(to.rotation, s),
+        scale: Vec3::ONE,
+    }
+}
+
+fn play_cutscene(
+    time: Res<GameTime>,
+    keyboard_input: Res<Input<KeyCode>>,
+    waypoints: Res<CutsceneWaypoints>,
+    rooms: Res<Rooms>,
+    current_room: Res<CurrentRoom>,
+    mut cutscene_player: ResMut<CutscenePlayer>,
+    mut input_block: ResMut<InputBlock>,
+    mut camera: Query<&mut Transform, With<FlyCam>>,
+) {
+    let room_offset = current_room
+        .id
+        .as_ref()
+        .and_then(|id| rooms.get(id))
+        .map(|room| room.offset)
+        .unwrap_or(Vec3::ZERO);
+    let cutscene_player = &mut *cutscene_player;
+    if let Some(timer) = cutscene_player.fade_out.as_mut() {
+        timer.tick(time.delta());
+    }
+    let playback = match cutscene_player.playback.as_mut() {
+        Some(playback) => playback,
+        None => return,
+    };
+
+    playback.elapsed += time.delta_seconds();
+    let skipped = keyboard_input.just_pressed(KeyCode::Escape)
+        || keyboard_input.just_pressed(KeyCode::Return);
+    let finished = skipped || playback.elapsed >= playback.cutscene.duration();
+    if let Ok(mut transform) = camera.single_mut() {
+        *transform = if finished {
+            playback.player_pose
+        } else {
+            sample(playback, &waypoints, room_offset)
+        };
+    }
+    if finished {
+        input_block.set("cutscene", false);
+        cutscene_player.fade_out = Some(Timer::from_seconds(playback.cutscene.fade_seconds, false));
+        cutscene_player.playback = None;
+    }
+}
+
+fn draw_fade(egui_context: Res<EguiContext>, cutscene_player: Res<CutscenePlayer>) {
+    let alpha = cutscene_player.fade_alpha();
+    if alpha <= 0.0 {
+        return;
+    }
+    let ctx = egui_context.ctx();
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("cutscene_fade"),
+    ));
+    painter.rect_filled(
+        ctx.input().screen_rect(),
+        0.0,
+        egui::Color32::from_black_alpha((alpha * 255.0) as u8),
+    );
+}
+
+/// Plays camera cutscenes: an intro flythrough once the first room has
+/// spawned and a shot of the door when the puzzle is solved. The flycam is frozen while a
+/// cutscene plays and Escape or Enter skips it.
+pub struct CutscenePlugin;
+
+impl Plugin for CutscenePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<PlayCutscene>();
+        app.init_resource::<Cutscenes>();
+        app.init_resource::<CutsceneWaypoints>();
+        app.init_resource::<CutscenePlayer>();
+        app.add_system(collect_waypoints.system());
+        app.add_system(trigger_cutscenes.system());
+        app.add_system(draw_fade.system().with_run_criteria(has_window.system()));
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            start_cutscene.system().label("start_cutscene"),
+        );
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            play_cutscene
+                .system()
+                .with_run_criteria(not_paused.system())
+                .after("start_cutscene")
+                .before(TransformSystem::TransformPropagate),
+        );
+    }
+}
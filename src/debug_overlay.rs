@@ -0,0 +1,172 @@
+//! Debug overlay. F4 shows what the pick and blacklight rays hit and the
+//! puzzle state they feed into, and marks the hit points in the room with
+//! `bevy_mod_raycast` debug cursors.
+
+use crate::{
+    has_window, BlacklightRaycastSet, BlacklightTarget, NamedEntity, PickingRaycastSet,
+    PlaceTarget, Player, PuzzleState, StatueHolders, Target,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_mod_raycast::{update_debug_cursor, DebugCursorMesh, RayCastSource, RaycastSystem};
+
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub open: bool,
+}
+
+fn describe_target(target: &Option<NamedEntity>) -> String {
+    match target {
+        Some(target) => format!("{} ({:?})", target.name, target.entity),
+        None => "nothing".to_string(),
+    }
+}
+
+fn describe_point(point: Vec3) -> String {
+    format!("({:.2}, {:.2}, {:.2})", point.x, point.y, point.z)
+}
+
+fn describe_ray<T: 'static + Send + Sync>(
+    ui: &mut egui::Ui,
+    label: &str,
+    source: &RayCastSource<T>,
+) {
+    ui.label(label);
+    match source.ray() {
+        Some(ray) => ui.label(format!(
+            "from {} towards {}",
+            describe_point(ray.origin()),
+            describe_point(ray.direction())
+        )),
+        None => ui.label("no ray"),
+    };
+    match source.intersect_top() {
+        Some((entity, intersection)) => ui.label(format!(
+            "hits {:?} at {}, {:.2} away",
+            entity,
+            describe_point(intersection.position()),
+            intersection.distance()
+        )),
+        None => ui.label("hits nothing"),
+    };
+    ui.end_row();
+}
+
+fn toggle_debug_overlay(keyboard_input: Res<Input<KeyCode>>, mut overlay: ResMut<DebugOverlay>) {
+    if keyboard_input.just_pressed(KeyCode::F4) {
+        overlay.open = !overlay.open;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn debug_overlay_ui(
+    egui_context: Res<EguiContext>,
+    mut overlay: ResMut<DebugOverlay>,
+    target: Res<Target>,
+    place_target: Res<PlaceTarget>,
+    blacklight_target: Res<BlacklightTarget>,
+    player: Res<Player>,
+    statue_holders: Res<StatueHolders>,
+    puzzle_state: Res<PuzzleState>,
+    picking_sources: Query<&RayCastSource<PickingRaycastSet>>,
+    blacklight_sources: Query<&RayCastSource<BlacklightRaycastSet>>,
+) {
+    if !overlay.open {
+        return;
+    }
+
+    egui::Window::new("Debug")
+        .open(&mut overlay.open)
+        .default_width(360.0)
+        .show(egui_context.ctx(), |ui| {
+            egui::Grid::new("debug_targets").show(ui, |ui| {
+                ui.label("Target");
+                ui.label(describe_target(&target.0));
+                ui.end_row();
+                ui.label("Place target");
+                ui.label(describe_target(&place_target.0));
+                ui.end_row();
+                ui.label("Blacklight target");
+                ui.label(describe_target(&blacklight_target.0));
+                ui.end_row();
+            });
+            ui.separator();
+            egui::Grid::new("debug_rays").show(ui, |ui| {
+                for source in picking_sources.iter() {
+                    describe_ray(ui, "Pick ray", source);
+                }
+                for source in blacklight_sources.iter() {
+                    describe_ray(ui, "Blacklight ray", source);
+                }
+            });
+            ui.separator();
+            let mut inventory: Vec<_> = player.inventory.iter().cloned().collect();
+            inventory.sort();
+            ui.label(format!("Inventory: {}", inventory.join(", ")));
+            ui.label(format!(
+                "Equipped: {} ({})",
+                player.equipped,
+                player.equipped_name()
+            ));
+            let mut held: Vec<_> = statue_holders
+                .held_statues
+                .iter()
+                .map(|(location, color)| format!("{:?}: {:?}", location, color))
+                .collect();
+            held.sort();
+            ui.label(format!("Statue holders: {}", held.join(", ")));
+            ui.label(format!("Puzzle solved: {}", puzzle_state.0));
+        });
+}
+
+/// The raycasting plugin moves the cursors every frame, so they are hidden
+/// again afterwards while the overlay is closed.
+fn show_debug_cursors(
+    overlay: Res<DebugOverlay>,
+    mut cursors: QuerySet<(
+        Query<&mut Visible, With<DebugCursorMesh<PickingRaycastSet>>>,
+        Query<&mut Visible, With<DebugCursorMesh<BlacklightRaycastSet>>>,
+    )>,
+) {
+    if overlay.open {
+        return;
+    }
+    for mut visible in cursors.q0_mut().iter_mut() {
+        visible.is_visible = false;
+    }
+    for mut visible in cursors.q1_mut().iter_mut() {
+        visible.is_visible = false;
+    }
+}
+
+pub struct DebugOverlayPlugin;
+
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<DebugOverlay>();
+        app.add_system(toggle_debug_overlay.system());
+        app.add_system(
+            debug_overlay_ui
+                .system()
+                .with_run_criteria(has_window.system()),
+        );
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            update_debug_cursor::<PickingRaycastSet>
+                .system()
+                .label("debug_cursors")
+                .after(RaycastSystem::UpdateRaycast),
+        );
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            update_debug_cursor::<BlacklightRaycastSet>
+                .system()
+                .label("debug_cursors")
+                .after(RaycastSystem::UpdateRaycast),
+        );
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            show_debug_cursors.system().after("debug_cursors"),
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{
+    audio::{PlaySound, SoundEffect},
+    game_time::GameTime,
+    pause::not_paused,
+    PuzzleAttempt, StatueHolder,
+};
+use bevy::prelude::*;
+
+const FLASH_SECONDS: f32 = 1.2;
+const FLASH_COUNT: f32 = 3.0;
+const FLASH_INTENSITY: f32 = 60.0;
+
+/// A light over the statue holders that blinks after a puzzle attempt.
+struct HolderFlash {
+    timer: Timer,
+}
+
+fn on_puzzle_attempt(
+    mut commands: Commands,
+    mut attempts: EventReader<PuzzleAttempt>,
+    holders: Query<(Entity, &StatueHolder, &GlobalTransform), With<Children>>,
+    flashes: Query<Entity, With<HolderFlash>>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    for attempt in attempts.iter() {
+        let middle = holders
+            .iter()
+            .find(|(_, holder, _)| holder.0 == "MiddleStatueHolder");
+        let (holder_entity, holder_transform) = match middle {
+            Some((entity, _, transform)) => (entity, transform),
+            None => continue,
+        };
+
+        if !attempt.correct {
+            sounds.send(PlaySound::at(SoundEffect::WrongPlacement, holder_entity));
+        }
+
+        for flash in flashes.iter() {
+            commands.entity(flash).despawn();
+        }
+        let color = if attempt.correct {
+            Color::GREEN
+        } else {
+            Color::RED
+        };
+        commands
+            .spawn_bundle(LightBundle {
+                light: Light {
+                    color,
+                    intensity: 0.0,
+                    range: 4.0,
+                    ..Light::default()
+                },
+                transform: Transform::from_translation(
+                    holder_transform.translation + Vec3::new(0.5, 1.0, 0.0),
+                ),
+                ..Default::default()
+            })
+            .insert(HolderFlash {
+                timer: Timer::from_seconds(FLASH_SECONDS, false),
+            });
+    }
+}
+
+fn animate_holder_flash(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut flashes: Query<(Entity, &mut HolderFlash, &mut Light)>,
+) {
+    for (entity, mut flash, mut light) in flashes.iter_mut() {
+        flash.timer.tick(time.delta());
+        if flash.timer.finished() {
+            commands.entity(entity).despawn();
+        } else {
+            let phase = flash.timer.percent() * FLASH_COUNT * std::f32::consts::PI;
+            light.intensity = FLASH_INTENSITY * phase.sin().abs();
+        }
+    }
+}
+
+/// Light and sound cues for `PuzzleAttempt` events.
+pub struct PuzzleFeedbackPlugin;
+
+impl Plugin for PuzzleFeedbackPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(on_puzzle_attempt.system());
+        app.add_system(
+            animate_holder_flash
+                .system()
+                .with_run_criteria(not_paused.system()),
+        );
+    }
+}
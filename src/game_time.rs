@@ -0,0 +1,66 @@
+//! The clock gameplay runs on. It follows bevy's `Time` but stands still
+//! while the game is `Paused`. In replays `replay_input` advances it by each
+//! recorded frame delta instead, so anything timed or animated ends up
+//! exactly where it did for the player, however fast the replay runs. bevy's
+//! own `Time` can't be driven from outside.
+
+use crate::pause::Paused;
+use bevy::{core::CoreSystem, prelude::*};
+use std::time::Duration;
+
+#[derive(Debug, Default)]
+pub struct GameTime {
+    delta_seconds: f32,
+    seconds_since_startup: f64,
+    /// only advanced through `advance`, not by bevy's `Time`
+    manual: bool,
+}
+
+impl GameTime {
+    /// A clock only `advance` moves.
+    pub fn manual() -> Self {
+        Self {
+            manual: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn advance(&mut self, delta_seconds: f32) {
+        self.delta_seconds = delta_seconds.max(0.0);
+        self.seconds_since_startup += self.delta_seconds as f64;
+    }
+
+    pub fn delta(&self) -> Duration {
+        Duration::from_secs_f32(self.delta_seconds)
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta_seconds
+    }
+
+    pub fn seconds_since_startup(&self) -> f64 {
+        self.seconds_since_startup
+    }
+}
+
+fn follow_time(time: Res<Time>, paused: Res<Paused>, mut game_time: ResMut<GameTime>) {
+    if game_time.manual {
+        return;
+    }
+    let delta_seconds = if paused.0 { 0.0 } else { time.delta_seconds() };
+    game_time.advance(delta_seconds);
+}
+
+/// Keeps `GameTime` in step with bevy's `Time`, minus pauses.
+pub struct GameTimePlugin;
+
+impl Plugin for GameTimePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<GameTime>();
+        app.init_resource::<Paused>();
+        app.add_system_to_stage(
+            CoreStage::First,
+            follow_time.system().after(CoreSystem::Time),
+        );
+    }
+}
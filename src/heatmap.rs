@@ -0,0 +1,201 @@
+//! Where players go. The camera position and look direction are sampled
+//! twice a second while in a room, in the room's own coordinates, and kept as
+//! CSV:
+//!
+//!     room,t,x,y,z,look_x,look_y,look_z
+//!     classroom,12.50,0.00,4.00,0.00,0.00,0.00,-1.00
+//!
+//! Native builds append them to `EscapeRoomConfig::path_log`, web builds
+//! offer them as a download from the playtest summary. The `heatmap` binary
+//! turns them into a top-down picture of each room.
+
+use crate::{
+    game_time::GameTime,
+    input::InputBlock,
+    rooms::{CurrentRoom, Rooms},
+};
+use bevy::prelude::*;
+use bevy_flycam::FlyCam;
+
+const SAMPLE_SECONDS: f32 = 0.5;
+
+pub const PATH_HEADER: &str = "room,t,x,y,z,look_x,look_y,look_z";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathSample {
+    pub room: String,
+    pub t: f64,
+    /// camera position in the room's coordinates
+    pub position: Vec3,
+    pub look: Vec3,
+}
+
+impl PathSample {
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{:.2},{:.2},{:.2},{:.2},{:.2},{:.2},{:.2}",
+            self.room,
+            self.t,
+            self.position.x,
+            self.position.y,
+            self.position.z,
+            self.look.x,
+            self.look.y,
+            self.look.z
+        )
+    }
+
+    pub fn from_csv(line: &str) -> Option<Self> {
+        let mut fields = line.trim().split(',');
+        let room = fields.next()?.to_string();
+        let numbers: Vec<f32> = fields.filter_map(|field| field.parse().ok()).collect();
+        match numbers.as_slice() {
+            [t, x, y, z, look_x, look_y, look_z] => Some(Self {
+                room,
+                t: *t as f64,
+                position: Vec3::new(*x, *y, *z),
+                look: Vec3::new(*look_x, *look_y, *look_z),
+            }),
+            _ => None,
+        }
+    }
+}
+
+pub struct PathSamples {
+    pub samples: Vec<PathSample>,
+    /// samples already written out
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    flushed: usize,
+    timer: Timer,
+}
+
+impl Default for PathSamples {
+    fn default() -> Self {
+        Self {
+            samples: Vec::new(),
+            flushed: 0,
+            timer: Timer::from_seconds(SAMPLE_SECONDS, true),
+        }
+    }
+}
+
+impl PathSamples {
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{}\n", PATH_HEADER);
+        for sample in self.samples.iter() {
+            csv.push_str(&sample.to_csv());
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+fn sample_path(
+    time: Res<GameTime>,
+    current: Res<CurrentRoom>,
+    rooms: Res<Rooms>,
+    input_block: Res<InputBlock>,
+    mut path: ResMut<PathSamples>,
+    camera: Query<&Transform, With<FlyCam>>,
+) {
+    if !path.timer.tick(time.delta()).just_finished() || input_block.is_blocked() {
+        return;
+    }
+    let room = match current.id.as_ref().and_then(|id| rooms.get(id)) {
+        Some(room) if current.is_ready() => room,
+        _ => return,
+    };
+    for transform in camera.iter() {
+        path.samples.push(PathSample {
+            room: room.id.clone(),
+            t: time.seconds_since_startup(),
+            position: transform.translation - room.offset,
+            look: transform.rotation * -Vec3::Z,
+        });
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn flush_path(config: Res<crate::EscapeRoomConfig>, mut path: ResMut<PathSamples>) {
+    use std::io::Write;
+
+    if path.flushed == path.samples.len() || config.path_log.is_empty() {
+        return;
+    }
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&config.path_log);
+    let result = file.and_then(|mut file| {
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{}", PATH_HEADER)?;
+        }
+        for sample in path.samples[path.flushed..].iter() {
+            writeln!(file, "{}", sample.to_csv())?;
+        }
+        Ok(())
+    });
+    if let Err(err) = result {
+        error!("unable to write {}: {}", config.path_log, err);
+    }
+    path.flushed = path.samples.len();
+}
+
+/// Samples the player's path through each room for heatmaps.
+pub struct HeatmapPlugin;
+
+impl Plugin for HeatmapPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PathSamples>();
+        app.add_system(sample_path.system().label("sample_path"));
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_system(flush_path.system().after("sample_path"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> PathSample {
+        PathSample {
+            room: "classroom".to_string(),
+            t: 12.5,
+            position: Vec3::new(0.25, 4.0, -1.5),
+            look: Vec3::new(0.0, 0.0, -1.0),
+        }
+    }
+
+    #[test]
+    fn sample_round_trips_through_csv() {
+        let line = sample().to_csv();
+        assert_eq!(line, "classroom,12.50,0.25,4.00,-1.50,0.00,0.00,-1.00");
+        assert_eq!(PathSample::from_csv(&line), Some(sample()));
+    }
+
+    #[test]
+    fn from_csv_tolerates_line_endings() {
+        let line = format!("{}\r\n", sample().to_csv());
+        assert_eq!(PathSample::from_csv(&line), Some(sample()));
+    }
+
+    #[test]
+    fn from_csv_rejects_the_header_and_short_lines() {
+        assert_eq!(PathSample::from_csv(PATH_HEADER), None);
+        assert_eq!(PathSample::from_csv("classroom,12.50,0.00,4.00"), None);
+        assert_eq!(PathSample::from_csv(""), None);
+    }
+
+    #[test]
+    fn samples_csv_starts_with_the_header() {
+        let samples = PathSamples {
+            samples: vec![sample(), sample()],
+            ..Default::default()
+        };
+        let csv = samples.to_csv();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], PATH_HEADER);
+        assert_eq!(PathSample::from_csv(lines[2]), Some(sample()));
+    }
+}
@@ -0,0 +1,51 @@
+use crate::settings::Settings;
+use bevy::prelude::*;
+use bevy_flycam::MovementSettings;
+use std::collections::HashSet;
+
+/// Reasons gameplay input is currently ignored, e.g. a cutscene or an open
+/// menu. While any are set the flycam is frozen and `keyboard_input_system`
+/// does nothing.
+#[derive(Default, Debug)]
+pub struct InputBlock(HashSet<&'static str>);
+
+impl InputBlock {
+    pub fn set(&mut self, reason: &'static str, blocked: bool) {
+        if blocked {
+            self.0.insert(reason);
+        } else {
+            self.0.remove(reason);
+        }
+    }
+
+    pub fn is_blocked(&self) -> bool {
+        !self.0.is_empty()
+    }
+}
+
+fn freeze_flycam(
+    input_block: Res<InputBlock>,
+    settings: Res<Settings>,
+    movement: Option<ResMut<MovementSettings>>,
+) {
+    let mut movement = match movement {
+        Some(movement) => movement,
+        None => return,
+    };
+    if input_block.is_blocked() {
+        movement.sensitivity = 0.0;
+        movement.speed = 0.0;
+    } else {
+        movement.sensitivity = settings.sensitivity;
+        movement.speed = settings.speed;
+    }
+}
+
+pub struct InputBlockPlugin;
+
+impl Plugin for InputBlockPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<InputBlock>();
+        app.add_system_to_stage(CoreStage::PreUpdate, freeze_flycam.system());
+    }
+}
@@ -0,0 +1,133 @@
+//! Interactions as events. `keyboard_input_system` only turns F1 into an
+//! `InteractionEvent`; `apply_interactions` changes the inventory and the
+//! statue holders and reports what happened with `ItemPickedUp`,
+//! `ItemPlaced` and `ItemRemoved`. Sounds, lighting, scripts and anything
+//! else subscribe to those instead of reaching into input code.
+
+use crate::{
+    parse_placed_statue, statue_color_for_name, statue_name_for_color, Location, NamedEntity,
+    Player, PuzzleAttempt, PuzzleDefinition, StatueHolders, Target,
+};
+use bevy::prelude::*;
+
+/// What the player is trying to do.
+#[derive(Debug, Clone)]
+pub enum InteractionEvent {
+    /// F1 on something pickable
+    Use { name: String, entity: Entity },
+    /// F1 on a statue holder while holding `item`
+    Place {
+        holder: String,
+        entity: Entity,
+        item: String,
+    },
+}
+
+/// `item` is the inventory name, e.g. `InvBallStatueRed`.
+#[derive(Debug, Clone)]
+pub struct ItemPickedUp {
+    pub item: String,
+    /// the scene node it was taken from, e.g. `BallStatueRed`
+    pub node: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ItemPlaced {
+    pub item: String,
+    pub holder: String,
+    /// the holder entity that was targeted
+    pub entity: Entity,
+}
+
+/// A placed statue taken back off its holder.
+#[derive(Debug, Clone)]
+pub struct ItemRemoved {
+    pub item: String,
+    pub location: Location,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_interactions(
+    mut commands: Commands,
+    mut interactions: EventReader<InteractionEvent>,
+    mut target: ResMut<Target>,
+    mut player: ResMut<Player>,
+    mut statue_holders: ResMut<StatueHolders>,
+    puzzle: Res<PuzzleDefinition>,
+    mut picked_up: EventWriter<ItemPickedUp>,
+    mut placed: EventWriter<ItemPlaced>,
+    mut removed: EventWriter<ItemRemoved>,
+    mut attempts: EventWriter<PuzzleAttempt>,
+) {
+    for interaction in interactions.iter() {
+        match interaction {
+            InteractionEvent::Use { name, entity } => match name.as_str() {
+                "BallStatueRed" | "BallStatueGreen" | "BallStatueBlue" | "BlacklightFlashlight" => {
+                    let inv_name = format!("Inv{}", name);
+                    player.inventory.insert(inv_name.clone());
+                    player.equipped = Player::item_index(&inv_name);
+                    commands.entity(*entity).despawn();
+                    target.0 = None;
+                    picked_up.send(ItemPickedUp {
+                        item: inv_name,
+                        node: name.clone(),
+                    });
+                }
+                _ => {
+                    if let Some((location, _color)) = parse_placed_statue(name) {
+                        if let Some(color) = statue_holders.held_statues.get(&location) {
+                            let item = statue_name_for_color(*color).to_string();
+                            statue_holders.remove(&mut player, location);
+                            removed.send(ItemRemoved { item, location });
+                        }
+                    }
+                }
+            },
+            InteractionEvent::Place {
+                holder,
+                entity,
+                item,
+            } => {
+                let color = match statue_color_for_name(item) {
+                    Some(color) => color,
+                    None => continue,
+                };
+                if !player.inventory.remove(item) {
+                    continue;
+                }
+                let place = NamedEntity {
+                    name: holder.clone(),
+                    entity: *entity,
+                };
+                statue_holders.place(&mut player, &place, color);
+                placed.send(ItemPlaced {
+                    item: item.clone(),
+                    holder: holder.clone(),
+                    entity: *entity,
+                });
+                if statue_holders.all_filled() {
+                    attempts.send(PuzzleAttempt {
+                        correct: statue_holders.solved(&puzzle),
+                    });
+                }
+            }
+        }
+    }
+}
+
+pub struct InteractionPlugin;
+
+impl Plugin for InteractionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<InteractionEvent>();
+        app.add_event::<ItemPickedUp>();
+        app.add_event::<ItemPlaced>();
+        app.add_event::<ItemRemoved>();
+        app.add_system(
+            apply_interactions
+                .system()
+                .label("apply_interactions")
+                .after("keyboard_input"),
+        );
+    }
+}
@@ -1,9 +1,79 @@
-use bevy::{prelude::*, scene::InstanceId};
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
 use bevy_egui::{egui, EguiContext, EguiPlugin};
 use bevy_flycam::{FlyCam, NoCameraPlayerPlugin};
 use bevy_mod_raycast::{DefaultRaycastingPlugin, RayCastMesh, RayCastSource, RaycastSystem};
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use wasm_bindgen::prelude::*;
+
+mod accessibility;
+mod achievements;
+mod analytics;
+mod audio;
+#[cfg(target_arch = "wasm32")]
+mod browser;
+mod campaign;
+mod console;
+mod cutscene;
+mod debug_overlay;
+mod feedback;
+mod game_time;
+mod heatmap;
+mod input;
+mod interaction;
+mod lighting;
+mod loading;
+mod localization;
+mod pause;
+mod puzzle_graph;
+mod replay;
+mod rooms;
+mod scene_ready;
+mod scripting;
+mod settings;
+mod storage;
+mod subtitles;
+mod touch;
+mod validation;
+
+pub use accessibility::AccessibilityPlugin;
+pub use achievements::{
+    Achievement, AchievementCondition, AchievementUnlocked, Achievements, AchievementsPlugin,
+    UnlockedAchievements,
+};
+pub use analytics::{
+    to_json_lines, Analytics, AnalyticsEntry, AnalyticsEvent, AnalyticsPlugin, AnalyticsSummary,
+};
+pub use audio::{AudioMixer, GameAudioPlugin, PlaySound, SoundEffect};
+pub use campaign::{Campaign, CampaignPlugin, CampaignProgress, MainMenu};
+pub use console::{Console, ConsoleCommand, ConsolePlugin};
+pub use cutscene::{CutscenePlayer, CutscenePlugin, PlayCutscene};
+pub use debug_overlay::{DebugOverlay, DebugOverlayPlugin};
+pub use game_time::{GameTime, GameTimePlugin};
+pub use heatmap::{HeatmapPlugin, PathSample, PathSamples, PATH_HEADER};
+pub use input::{InputBlock, InputBlockPlugin};
+pub use interaction::{InteractionEvent, InteractionPlugin, ItemPickedUp, ItemPlaced, ItemRemoved};
+pub use lighting::{LightingCommand, LightingMode, LightingPlugin};
+pub use loading::{LoadingAssets, LoadingPlugin};
+pub use localization::{
+    FluentSource, Localization, LocalizationPlugin, FALLBACK_LANGUAGE, LANGUAGES,
+};
+pub use pause::{PausePlugin, Paused};
+pub use puzzle_graph::{
+    Goal, GoalCompleted, GoalCondition, GoalProgress, PuzzleGraph, PuzzleGraphPlugin, WorldChange,
+};
+pub use replay::{RecordedFrame, Recording, RecordingPlugin, Replay, ReplayPlugin};
+pub use rooms::{CurrentRoom, DoorLink, EnterRoom, RoomDefinition, Rooms, RoomsPlugin};
+pub use scene_ready::{PendingScenes, SceneKind, SceneReady, SceneReadyPlugin};
+pub use scripting::{RoomScripts, ScriptHook, ScriptingPlugin};
+pub use settings::{Settings, SettingsPlugin};
+pub use storage::StorageConfig;
+pub use subtitles::{Subtitle, SubtitlesPlugin};
+pub use touch::{TouchControls, TouchPlugin};
+// for the validate-scene tool, the game itself goes through the module
+#[cfg(not(target_arch = "wasm32"))]
+pub use validation::{
+    required_room_nodes, validate_scene, SceneNode, ValidationError, PLAYER_SCENE_NODES,
+};
 
 enum TextureIds {
     BlacklightFlashlightSelTextureId,
@@ -30,22 +100,21 @@ impl Into<u64> for TextureIds {
     }
 }
 
-#[derive(Default)]
-pub struct EquippedInstance(Option<InstanceId>);
-
 pub fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut scene_spawner: ResMut<SceneSpawner>,
-    mut scene_instance: ResMut<EquippedInstance>,
+    mut pending_scenes: ResMut<PendingScenes>,
+    mut loading: ResMut<LoadingAssets>,
+    config: Res<EscapeRoomConfig>,
 ) {
-    commands.spawn_scene(asset_server.load("er.gltf#Scene0"));
+    let player_scene: Handle<Scene> = asset_server.load(config.player_scene.as_str());
+    loading.add(&config.player_scene, player_scene.clone_untyped());
     commands
         .spawn_bundle(PerspectiveCameraBundle::default())
         .with_children(|parent| {
-            let instance_id = scene_spawner
-                .spawn_as_child(asset_server.load("pl.gltf#Scene0"), parent.parent_entity());
-            scene_instance.0 = Some(instance_id);
+            let instance_id = scene_spawner.spawn_as_child(player_scene, parent.parent_entity());
+            pending_scenes.watch(instance_id, SceneKind::Player);
         })
         .with_children(|parent| {
             parent.spawn_bundle(LightBundle {
@@ -68,7 +137,7 @@ pub fn setup(
 /// this component indicates what entities should rotate
 pub struct Rotates;
 
-pub fn rotator_system(time: Res<Time>, mut query: Query<&mut Transform, With<Rotates>>) {
+pub fn rotator_system(time: Res<GameTime>, mut query: Query<&mut Transform, With<Rotates>>) {
     for mut transform in query.iter_mut() {
         *transform = Transform::from_rotation(Quat::from_rotation_y(
             (4.0 * std::f32::consts::PI / 20.0) * time.delta_seconds(),
@@ -76,45 +145,55 @@ pub fn rotator_system(time: Res<Time>, mut query: Query<&mut Transform, With<Rot
     }
 }
 
-fn load_assets(mut egui_context: ResMut<EguiContext>, assets: Res<AssetServer>) {
-    let texture_handle = assets.load("inventory_slot.png");
-    egui_context.set_egui_texture(TextureIds::InventoryTextureId.into(), texture_handle);
-    let texture_handle = assets.load("inventory_slot_sel.png");
-    egui_context.set_egui_texture(TextureIds::InventorySelTextureId.into(), texture_handle);
-    let texture_handle = assets.load("blue_ball_statue_sel.png");
-    egui_context.set_egui_texture(TextureIds::BlueStatueSelTextureId.into(), texture_handle);
-    let texture_handle = assets.load("blue_ball_statue.png");
-    egui_context.set_egui_texture(TextureIds::BlueStatueTextureId.into(), texture_handle);
-    let texture_handle = assets.load("green_ball_statue_sel.png");
-    egui_context.set_egui_texture(TextureIds::GreenStatueSelTextureId.into(), texture_handle);
-    let texture_handle = assets.load("green_ball_statue.png");
-    egui_context.set_egui_texture(TextureIds::GreenStatueTextureId.into(), texture_handle);
-    let texture_handle = assets.load("red_ball_statue.png");
-    egui_context.set_egui_texture(TextureIds::RedStatueTextureId.into(), texture_handle);
-    let texture_handle = assets.load("red_ball_statue_sel.png");
-    egui_context.set_egui_texture(TextureIds::RedStatueSelTextureId.into(), texture_handle);
-    let texture_handle = assets.load("blacklight_flashlight_sel.png");
-    egui_context.set_egui_texture(
-        TextureIds::BlacklightFlashlightSelTextureId.into(),
-        texture_handle,
+fn load_assets(
+    mut egui_context: ResMut<EguiContext>,
+    assets: Res<AssetServer>,
+    config: Res<EscapeRoomConfig>,
+    mut loading: ResMut<LoadingAssets>,
+) {
+    let mut load = |file: &str, id: TextureIds| {
+        let path = config.texture(file);
+        let texture_handle: Handle<Texture> = assets.load(path.as_str());
+        loading.add(&path, texture_handle.clone_untyped());
+        egui_context.set_egui_texture(id.into(), texture_handle);
+    };
+    load("inventory_slot.png", TextureIds::InventoryTextureId);
+    load("inventory_slot_sel.png", TextureIds::InventorySelTextureId);
+    load(
+        "blue_ball_statue_sel.png",
+        TextureIds::BlueStatueSelTextureId,
+    );
+    load("blue_ball_statue.png", TextureIds::BlueStatueTextureId);
+    load(
+        "green_ball_statue_sel.png",
+        TextureIds::GreenStatueSelTextureId,
+    );
+    load("green_ball_statue.png", TextureIds::GreenStatueTextureId);
+    load("red_ball_statue.png", TextureIds::RedStatueTextureId);
+    load("red_ball_statue_sel.png", TextureIds::RedStatueSelTextureId);
+    load(
+        "blacklight_flashlight_sel.png",
+        TextureIds::BlacklightFlashlightSelTextureId,
+    );
+    load(
+        "blacklight_flashlight.png",
+        TextureIds::BlacklightFlashlightTextureId,
+    );
+    load("PosterBlueUV.png", TextureIds::BluePosterTextureId);
+    load(
+        "PosterBlueUVBlackLight.png",
+        TextureIds::BluePosterUvTextureId,
+    );
+    load("PosterRedUV.png", TextureIds::RedPosterTextureId);
+    load(
+        "PosterRedUVBlackLight.png",
+        TextureIds::RedPosterUvTextureId,
     );
-    let texture_handle = assets.load("blacklight_flashlight.png");
-    egui_context.set_egui_texture(
-        TextureIds::BlacklightFlashlightTextureId.into(),
-        texture_handle,
+    load("PosterGreenUV.png", TextureIds::GreenPosterTextureId);
+    load(
+        "PosterGreenUVBlackLight.png",
+        TextureIds::GreenPosterUvTextureId,
     );
-    let texture_handle = assets.load("PosterBlueUV.png");
-    egui_context.set_egui_texture(TextureIds::BluePosterTextureId.into(), texture_handle);
-    let texture_handle = assets.load("PosterBlueUVBlackLight.png");
-    egui_context.set_egui_texture(TextureIds::BluePosterUvTextureId.into(), texture_handle);
-    let texture_handle = assets.load("PosterRedUV.png");
-    egui_context.set_egui_texture(TextureIds::RedPosterTextureId.into(), texture_handle);
-    let texture_handle = assets.load("PosterRedUVBlackLight.png");
-    egui_context.set_egui_texture(TextureIds::RedPosterUvTextureId.into(), texture_handle);
-    let texture_handle = assets.load("PosterGreenUV.png");
-    egui_context.set_egui_texture(TextureIds::GreenPosterTextureId.into(), texture_handle);
-    let texture_handle = assets.load("PosterGreenUVBlackLight.png");
-    egui_context.set_egui_texture(TextureIds::GreenPosterUvTextureId.into(), texture_handle);
 }
 
 const ITEMS: &[&'static str] = &[
@@ -138,7 +217,28 @@ fn maybe_equipped(
     }
 }
 
-fn ui_example(egui_context: Res<EguiContext>, player: Res<Player>) {
+/// Run criterion for the egui systems: `EguiContext::ctx` panics without a
+/// window, e.g. in the headless `replay` binary.
+pub(crate) fn has_window(windows: Res<Windows>) -> ShouldRun {
+    if windows.get_primary().is_some() {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+/// Screen rects of the inventory slots in `ITEMS` order, for tapping them.
+#[derive(Default)]
+struct InventorySlots(Vec<egui::Rect>);
+
+fn ui_example(
+    egui_context: Res<EguiContext>,
+    player: Res<Player>,
+    settings: Res<Settings>,
+    localization: Res<Localization>,
+    mut slots: ResMut<InventorySlots>,
+) {
+    slots.0.clear();
     let textures: Vec<_> = ITEMS
         .iter()
         .enumerate()
@@ -181,15 +281,28 @@ fn ui_example(egui_context: Res<EguiContext>, player: Res<Player>) {
             }
         })
         .collect();
-    egui::Window::new("Inventory")
+    egui::Window::new(localization.tr("inventory-title"))
+        .id(egui::Id::new("inventory"))
         .default_width(100.0)
         .show(egui_context.ctx(), |ui| {
             ui.vertical_centered(|ui| {
-                for texture_id in textures {
-                    ui.add(egui::widgets::Image::new(
+                for (texture_id, name) in textures.into_iter().zip(ITEMS.iter()) {
+                    let slot = ui.add(egui::widgets::Image::new(
                         egui::TextureId::User(texture_id),
                         [80.0, 80.0],
                     ));
+                    slots.0.push(slot.rect);
+                    if !player.inventory.contains(*name) {
+                        continue;
+                    }
+                    if let Some(color) = statue_color_for_name(name) {
+                        if settings.color_symbols {
+                            let corner = slot.rect.right_top() + egui::vec2(-22.0, 4.0);
+                            let rect = egui::Rect::from_min_size(corner, egui::vec2(18.0, 18.0));
+                            accessibility::paint_symbol(ui.painter(), rect, color);
+                        }
+                    }
+                    slot.on_hover_text(localization.tr(&format!("item-{}", name)));
                 }
             });
         });
@@ -265,7 +378,6 @@ fn angle_to_radians(degrees: f32) -> f32 {
 // z - left and right in frame
 // x - front to back in frame
 fn open_door(transform: &mut Transform, angle: f32) {
-    dbg!(&transform);
     const DOOR_WIDTH: f32 = 3.95805;
     const HALF_DOOR_WIDTH: f32 = DOOR_WIDTH / 2.0;
     const DOOR_THICKNESS: f32 = 0.867563;
@@ -281,48 +393,32 @@ fn open_door(transform: &mut Transform, angle: f32) {
 
 fn keyboard_input_system(
     keyboard_input: Res<Input<KeyCode>>,
-    mut target: ResMut<Target>,
-    place_target: ResMut<PlaceTarget>,
+    target: Res<Target>,
+    place_target: Res<PlaceTarget>,
     mut player: ResMut<Player>,
-    mut statue_holders: ResMut<StatueHolders>,
-    mut commands: Commands,
     mut _puzzle_door: Query<(&mut InsideDoor, &mut Transform)>,
     mut _door_angle: ResMut<InsideDoorAngle>,
+    input_block: Res<InputBlock>,
+    mut interactions: EventWriter<InteractionEvent>,
 ) {
+    if input_block.is_blocked() {
+        return;
+    }
+
     if keyboard_input.just_pressed(KeyCode::F1) {
         if let Some(pick_target) = target.0.as_ref() {
-            let target_name = pick_target.name.to_string();
-            match target_name.as_str() {
-                "BallStatueRed" | "BallStatueGreen" | "BallStatueBlue" | "BlacklightFlashlight" => {
-                    let inv_name = format!("Inv{}", target_name);
-                    player.inventory.insert(inv_name.clone());
-                    player.equipped = Player::item_index(&inv_name);
-                    commands.entity(pick_target.entity).despawn();
-                    target.0 = None;
-                }
-                _ => {
-                    let parsed = parse_placed_statue(&target_name);
-                    if let Some((location, _color)) = parsed {
-                        statue_holders.remove(&mut player, location);
-                    }
-                }
-            }
+            interactions.send(InteractionEvent::Use {
+                name: pick_target.name.clone(),
+                entity: pick_target.entity,
+            });
         } else if let Some(place) = place_target.0.as_ref() {
             let equipped_name = player.equipped_name();
-            match equipped_name {
-                "InvBallStatueBlue" => {
-                    player.inventory.remove(equipped_name);
-                    statue_holders.place(&mut player, place, StatueColor::Blue);
-                }
-                "InvBallStatueGreen" => {
-                    player.inventory.remove(equipped_name);
-                    statue_holders.place(&mut player, place, StatueColor::Green);
-                }
-                "InvBallStatueRed" => {
-                    player.inventory.remove(equipped_name);
-                    statue_holders.place(&mut player, place, StatueColor::Red);
-                }
-                _ => (),
+            if !equipped_name.is_empty() {
+                interactions.send(InteractionEvent::Place {
+                    holder: place.name.clone(),
+                    entity: place.entity,
+                    item: equipped_name.to_string(),
+                });
             }
         }
     }
@@ -356,20 +452,17 @@ fn keyboard_input_system(
     }
 }
 
-#[derive(Default)]
-struct Done(bool);
-
 struct BlacklightFlashlight;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum StatueColor {
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum StatueColor {
     Red,
     Green,
     Blue,
 }
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
-enum Location {
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Location {
     Left,
     Middle,
     Right,
@@ -428,6 +521,11 @@ impl Default for InsideDoorAngle {
     }
 }
 
+/// The `InsideDoor` state the door last finished swinging to, `None` before
+/// the first swing. The console sets it when it props the door open.
+#[derive(Default)]
+struct InsideDoorSwung(Option<bool>);
+
 #[derive(Debug)]
 struct Player {
     equipped: usize,
@@ -468,6 +566,15 @@ impl Player {
     }
 }
 
+fn statue_color_for_name(name: &str) -> Option<StatueColor> {
+    match name {
+        "InvBallStatueRed" => Some(StatueColor::Red),
+        "InvBallStatueGreen" => Some(StatueColor::Green),
+        "InvBallStatueBlue" => Some(StatueColor::Blue),
+        _ => None,
+    }
+}
+
 fn statue_name_for_color(color: StatueColor) -> &'static str {
     match color {
         StatueColor::Red => "InvBallStatueRed",
@@ -476,6 +583,22 @@ fn statue_name_for_color(color: StatueColor) -> &'static str {
     }
 }
 
+/// Which statue color solves each holder in the current room.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PuzzleDefinition {
+    pub solution: HashMap<Location, StatueColor>,
+}
+
+impl Default for PuzzleDefinition {
+    fn default() -> Self {
+        let mut solution = HashMap::new();
+        solution.insert(Location::Left, StatueColor::Blue);
+        solution.insert(Location::Middle, StatueColor::Red);
+        solution.insert(Location::Right, StatueColor::Green);
+        Self { solution }
+    }
+}
+
 #[derive(Default, Debug)]
 struct StatueHolders {
     held_statues: HashMap<Location, StatueColor>,
@@ -505,10 +628,16 @@ impl StatueHolders {
         self.held_statues.remove(&location);
     }
 
-    fn solved(&self) -> bool {
-        self.held_statues.get(&Location::Left) == Some(&StatueColor::Blue)
-            && self.held_statues.get(&Location::Middle) == Some(&StatueColor::Red)
-            && self.held_statues.get(&Location::Right) == Some(&StatueColor::Green)
+    fn all_filled(&self) -> bool {
+        self.held_statues.len() == 3
+    }
+
+    fn solved(&self, puzzle: &PuzzleDefinition) -> bool {
+        !puzzle.solution.is_empty()
+            && puzzle
+                .solution
+                .iter()
+                .all(|(location, color)| self.held_statues.get(location) == Some(color))
     }
 }
 
@@ -583,223 +712,249 @@ fn make_children_placed_statues(
     }
 }
 
-fn make_children_inside_door(commands: &mut Commands, children: &Children) {
+fn make_children_inside_door(commands: &mut Commands, children: &Children, open: bool) {
     for c in children.iter() {
-        commands.entity(*c).insert(InsideDoor(false));
+        commands.entity(*c).insert(InsideDoor(open));
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn tag_stuff(
     mut commands: Commands,
-    mut done: ResMut<Done>,
-    equipped_instance: Res<EquippedInstance>,
+    mut scenes_ready: EventReader<SceneReady>,
     entities: Query<(Entity, &Name, &Children, &Transform)>,
+    names: Query<(&Name, Option<&Children>)>,
     scene_spawner: Res<SceneSpawner>,
+    rooms: Res<Rooms>,
+    room_scripts: Res<RoomScripts>,
+    puzzle_state: Res<PuzzleState>,
 ) {
-    if !done.0 {
-        if let Some(instance_id) = equipped_instance.0 {
-            if let Some(_entity_iter) = scene_spawner.iter_instance_entities(instance_id) {
-                done.0 = true;
+    for ready in scenes_ready.iter() {
+        let instance_entities = match scene_spawner.iter_instance_entities(ready.instance) {
+            Some(instance_entities) => instance_entities,
+            None => continue,
+        };
+        let mut nodes = Vec::new();
+        for entity in instance_entities {
+            if let Ok((name, node_children)) = names.get(entity) {
+                nodes.push(validation::SceneNode {
+                    name: name.to_string(),
+                    has_children: node_children.is_some(),
+                });
             }
-
-            for (e, n, children, t) in entities.iter() {
-                let name = n.as_str();
-                match name {
-                    "BlacklightFlashlight" => {
-                        commands.entity(e).insert(BlacklightFlashlight);
-                        make_children_pickable(&mut commands, &e, children, name);
-                        ()
-                    }
-                    "BallStatueGreen" => {
-                        commands.entity(e).insert(BallStatue(StatueColor::Green));
-                        make_children_pickable(&mut commands, &e, children, name);
-                    }
-                    "BallStatueBlue" => {
-                        commands.entity(e).insert(BallStatue(StatueColor::Blue));
-                        make_children_pickable(&mut commands, &e, children, name);
-                    }
-                    "BallStatueRed" => {
-                        commands.entity(e).insert(BallStatue(StatueColor::Red));
-                        make_children_pickable(&mut commands, &e, children, name);
-                    }
-                    "LeftStatueHolder" => {
-                        make_children_placeable(&mut commands, &e, children, name);
-                    }
-                    "MiddleStatueHolder" => {
-                        make_children_placeable(&mut commands, &e, children, name);
-                    }
-                    "RightStatueHolder" => {
-                        make_children_placeable(&mut commands, &e, children, name);
-                    }
-                    "BallStatueRedPlacedRight" => {
-                        make_children_placed_statues(
-                            &mut commands,
-                            &e,
-                            children,
-                            StatueColor::Red,
-                            Location::Right,
-                            name,
-                        );
-                    }
-                    "BallStatueGreenPlacedRight" => {
-                        make_children_placed_statues(
-                            &mut commands,
-                            &e,
-                            children,
-                            StatueColor::Green,
-                            Location::Right,
-                            name,
-                        );
-                    }
-                    "BallStatueBluePlacedRight" => {
-                        make_children_placed_statues(
-                            &mut commands,
-                            &e,
-                            children,
-                            StatueColor::Blue,
-                            Location::Right,
-                            name,
-                        );
-                    }
-                    "BallStatueRedPlacedMid" => {
-                        make_children_placed_statues(
-                            &mut commands,
-                            &e,
-                            children,
-                            StatueColor::Red,
-                            Location::Middle,
-                            name,
-                        );
-                    }
-                    "BallStatueGreenPlacedMid" => {
-                        make_children_placed_statues(
-                            &mut commands,
-                            &e,
-                            children,
-                            StatueColor::Green,
-                            Location::Middle,
-                            name,
-                        );
-                    }
-                    "BallStatueBluePlacedMid" => {
-                        make_children_placed_statues(
-                            &mut commands,
-                            &e,
-                            children,
-                            StatueColor::Blue,
-                            Location::Middle,
-                            name,
-                        );
-                    }
-                    "BallStatueRedPlacedLeft" => {
-                        make_children_placed_statues(
-                            &mut commands,
-                            &e,
-                            children,
-                            StatueColor::Red,
-                            Location::Left,
-                            name,
-                        );
-                    }
-                    "BallStatueGreenPlacedLeft" => {
-                        make_children_placed_statues(
-                            &mut commands,
-                            &e,
-                            children,
-                            StatueColor::Green,
-                            Location::Left,
-                            name,
-                        );
-                    }
-                    "BallStatueBluePlacedLeft" => {
-                        make_children_placed_statues(
-                            &mut commands,
-                            &e,
-                            children,
-                            StatueColor::Blue,
-                            Location::Left,
-                            name,
-                        );
-                    }
-                    "RedPoster" => {
-                        make_children_posters(
-                            &mut commands,
-                            children,
-                            name,
-                            StatueColor::Red,
-                            false,
-                        );
-                    }
-                    "GreenPoster" => {
-                        make_children_posters(
-                            &mut commands,
-                            children,
-                            name,
-                            StatueColor::Green,
-                            false,
-                        );
-                    }
-                    "BluePoster" => {
-                        make_children_posters(
-                            &mut commands,
-                            children,
-                            name,
-                            StatueColor::Blue,
-                            false,
-                        );
-                    }
-                    "RedPosterUV" => {
-                        make_children_posters(
-                            &mut commands,
-                            children,
-                            "RedPoster",
-                            StatueColor::Red,
-                            true,
-                        );
-                    }
-                    "GreenPosterUV" => {
-                        make_children_posters(
-                            &mut commands,
-                            children,
-                            "GreenPoster",
-                            StatueColor::Green,
-                            true,
-                        );
-                    }
-                    "BluePosterUV" => {
-                        make_children_posters(
-                            &mut commands,
-                            children,
-                            "BluePoster",
-                            StatueColor::Blue,
-                            true,
-                        );
-                    }
-                    "InsideDoor" => make_children_inside_door(&mut commands, children),
-                    _ => {
-                        if name.starts_with("Inv") {
-                            commands.entity(e).insert(Inventory(n.to_string()));
-                            commands.entity(e).insert(Visible {
+            let (e, n, children, t) = match entities.get(entity) {
+                Ok(node) => node,
+                Err(_) => continue,
+            };
+            let name = n.as_str();
+            match name {
+                "BlacklightFlashlight" => {
+                    commands.entity(e).insert(BlacklightFlashlight);
+                    make_children_pickable(&mut commands, &e, children, name);
+                    ()
+                }
+                "BallStatueGreen" => {
+                    commands.entity(e).insert(BallStatue(StatueColor::Green));
+                    make_children_pickable(&mut commands, &e, children, name);
+                }
+                "BallStatueBlue" => {
+                    commands.entity(e).insert(BallStatue(StatueColor::Blue));
+                    make_children_pickable(&mut commands, &e, children, name);
+                }
+                "BallStatueRed" => {
+                    commands.entity(e).insert(BallStatue(StatueColor::Red));
+                    make_children_pickable(&mut commands, &e, children, name);
+                }
+                "LeftStatueHolder" => {
+                    make_children_placeable(&mut commands, &e, children, name);
+                }
+                "MiddleStatueHolder" => {
+                    make_children_placeable(&mut commands, &e, children, name);
+                }
+                "RightStatueHolder" => {
+                    make_children_placeable(&mut commands, &e, children, name);
+                }
+                "BallStatueRedPlacedRight" => {
+                    make_children_placed_statues(
+                        &mut commands,
+                        &e,
+                        children,
+                        StatueColor::Red,
+                        Location::Right,
+                        name,
+                    );
+                }
+                "BallStatueGreenPlacedRight" => {
+                    make_children_placed_statues(
+                        &mut commands,
+                        &e,
+                        children,
+                        StatueColor::Green,
+                        Location::Right,
+                        name,
+                    );
+                }
+                "BallStatueBluePlacedRight" => {
+                    make_children_placed_statues(
+                        &mut commands,
+                        &e,
+                        children,
+                        StatueColor::Blue,
+                        Location::Right,
+                        name,
+                    );
+                }
+                "BallStatueRedPlacedMid" => {
+                    make_children_placed_statues(
+                        &mut commands,
+                        &e,
+                        children,
+                        StatueColor::Red,
+                        Location::Middle,
+                        name,
+                    );
+                }
+                "BallStatueGreenPlacedMid" => {
+                    make_children_placed_statues(
+                        &mut commands,
+                        &e,
+                        children,
+                        StatueColor::Green,
+                        Location::Middle,
+                        name,
+                    );
+                }
+                "BallStatueBluePlacedMid" => {
+                    make_children_placed_statues(
+                        &mut commands,
+                        &e,
+                        children,
+                        StatueColor::Blue,
+                        Location::Middle,
+                        name,
+                    );
+                }
+                "BallStatueRedPlacedLeft" => {
+                    make_children_placed_statues(
+                        &mut commands,
+                        &e,
+                        children,
+                        StatueColor::Red,
+                        Location::Left,
+                        name,
+                    );
+                }
+                "BallStatueGreenPlacedLeft" => {
+                    make_children_placed_statues(
+                        &mut commands,
+                        &e,
+                        children,
+                        StatueColor::Green,
+                        Location::Left,
+                        name,
+                    );
+                }
+                "BallStatueBluePlacedLeft" => {
+                    make_children_placed_statues(
+                        &mut commands,
+                        &e,
+                        children,
+                        StatueColor::Blue,
+                        Location::Left,
+                        name,
+                    );
+                }
+                "RedPoster" => {
+                    make_children_posters(&mut commands, children, name, StatueColor::Red, false);
+                }
+                "GreenPoster" => {
+                    make_children_posters(&mut commands, children, name, StatueColor::Green, false);
+                }
+                "BluePoster" => {
+                    make_children_posters(&mut commands, children, name, StatueColor::Blue, false);
+                }
+                "RedPosterUV" => {
+                    make_children_posters(
+                        &mut commands,
+                        children,
+                        "RedPoster",
+                        StatueColor::Red,
+                        true,
+                    );
+                }
+                "GreenPosterUV" => {
+                    make_children_posters(
+                        &mut commands,
+                        children,
+                        "GreenPoster",
+                        StatueColor::Green,
+                        true,
+                    );
+                }
+                "BluePosterUV" => {
+                    make_children_posters(
+                        &mut commands,
+                        children,
+                        "BluePoster",
+                        StatueColor::Blue,
+                        true,
+                    );
+                }
+                "Button" => {
+                    make_children_pickable(&mut commands, &e, children, name);
+                }
+                // a room solved on an earlier visit spawns with its door open
+                "InsideDoor" => make_children_inside_door(&mut commands, children, puzzle_state.0),
+                _ if room_scripts.contains(name) => {
+                    make_children_pickable(&mut commands, &e, children, name);
+                }
+                _ => {
+                    if name.starts_with("Inv") {
+                        commands.entity(e).insert(Inventory(n.to_string()));
+                        commands.entity(e).insert(Visible {
+                            is_visible: false,
+                            is_transparent: false,
+                        });
+                        for c in children.iter() {
+                            commands.entity(*c).insert(Inventory(n.to_string()));
+                            commands.entity(*c).insert(Visible {
                                 is_visible: false,
                                 is_transparent: false,
                             });
-                            for c in children.iter() {
-                                commands.entity(*c).insert(Inventory(n.to_string()));
-                                commands.entity(*c).insert(Visible {
-                                    is_visible: false,
-                                    is_transparent: false,
-                                });
-                            }
-                        } else if name == "BlacklightSpot" {
-                            commands.spawn_bundle(LightBundle {
-                                transform: *t,
-                                ..Default::default()
-                            });
                         }
+                    } else if name == "BlacklightSpot" {
+                        commands.spawn_bundle(LightBundle {
+                            transform: *t,
+                            ..Default::default()
+                        });
                     }
                 }
             }
         }
+        report_invalid_nodes(&ready.kind, &nodes, &rooms);
+    }
+}
+
+fn report_invalid_nodes(kind: &SceneKind, nodes: &[validation::SceneNode], rooms: &Rooms) {
+    let (scene, required) = match kind {
+        SceneKind::Player => (
+            "player scene".to_string(),
+            validation::PLAYER_SCENE_NODES
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+        ),
+        SceneKind::Room(id) => {
+            let room = rooms.get(id);
+            let puzzle = room.map(|room| room.puzzle.clone()).unwrap_or_default();
+            (
+                format!("room {}", id),
+                validation::required_room_nodes(&puzzle, room),
+            )
+        }
+    };
+    for error in validation::validate_scene(&scene, nodes, &required) {
+        error!("{}", error);
     }
 }
 
@@ -857,6 +1012,8 @@ fn shine_on_poster(
     mut target: ResMut<BlacklightTarget>,
 ) {
     if let Some(blacklight_camera) = blacklight_camera_query.iter().last() {
+        // cleared as soon as the beam leaves the poster, or a poster stays lit
+        *target = BlacklightTarget(None);
         if let Some((illuminated_entity, _intersection)) = blacklight_camera.intersect_top() {
             if let Ok(poster) = entities.get_component::<Poster>(illuminated_entity) {
                 *target = BlacklightTarget(Some(NamedEntity {
@@ -868,23 +1025,260 @@ fn shine_on_poster(
     }
 }
 
+/// Sent when a statue is placed and every holder is occupied.
+#[derive(Debug, Clone, Copy)]
+pub struct PuzzleAttempt {
+    pub correct: bool,
+}
+
 fn check_for_solution(
     mut solved: ResMut<PuzzleState>,
     statue_holders: Res<StatueHolders>,
-    mut puzzle_door: Query<(&mut InsideDoor, &mut Transform)>,
+    puzzle: Res<PuzzleDefinition>,
+    mut puzzle_door: Query<(Entity, &mut InsideDoor)>,
+    mut sounds: EventWriter<PlaySound>,
+    mut hooks: EventWriter<ScriptHook>,
+) {
+    if solved.0 || !statue_holders.solved(&puzzle) {
+        return;
+    }
+    // the console can fill the holders before a room is spawned, the door
+    // opens once there is one
+    let (entity, mut door) = match puzzle_door.single_mut() {
+        Ok(door) => door,
+        Err(_) => return,
+    };
+    info!("solved");
+    solved.0 = true;
+    door.0 = true;
+    sounds.send(PlaySound::at(SoundEffect::DoorCreak, entity));
+    hooks.send(ScriptHook::Solved);
+}
+
+const DOOR_OPEN_ANGLE: f32 = 90.0;
+const DOOR_SWING_DEGREES_PER_SECOND: f32 = 40.0;
+
+/// Swings the door towards open or shut whenever `InsideDoor` changes, and
+/// leaves it alone in between so the console can prop it at any angle.
+fn swing_inside_door(
+    time: Res<GameTime>,
+    settings: Res<Settings>,
+    cutscene_player: Res<CutscenePlayer>,
+    mut door_angle: ResMut<InsideDoorAngle>,
+    mut swung: ResMut<InsideDoorSwung>,
+    mut puzzle_door: Query<(&InsideDoor, &mut Transform)>,
 ) {
-    if !solved.0 {
-        if statue_holders.solved() {
-            println!("solved!");
-            solved.0 = true;
-            let (mut door, mut transform) = puzzle_door.single_mut().expect("door");
-            door.0 = true;
-            open_door(&mut transform, 90.0);
+    let open = puzzle_door.iter_mut().any(|(door, _)| door.0);
+    if swung.0 == Some(open) {
+        return;
+    }
+
+    let target = if open { DOOR_OPEN_ANGLE } else { 0.0 };
+    let remaining = target - door_angle.0;
+    let step = DOOR_SWING_DEGREES_PER_SECOND * time.delta_seconds();
+    // with reduced motion the door snaps while a cutscene's fade hides it;
+    // opened from the console or a script nothing would, so it swings
+    let snap = settings.reduced_motion && cutscene_player.is_playing();
+    let arrived = snap || remaining.abs() <= step;
+    door_angle.0 = if arrived {
+        target
+    } else {
+        door_angle.0 + step * remaining.signum()
+    };
+    for (_, mut transform) in puzzle_door.iter_mut() {
+        open_door(&mut transform, door_angle.0);
+    }
+    if arrived {
+        swung.0 = Some(open);
+    }
+}
+
+/// Asset paths the game loads outside of the campaign manifest, and where
+/// playtest analytics go.
+#[derive(Debug, Clone)]
+pub struct EscapeRoomConfig {
+    /// glTF scene with the first person inventory models
+    pub player_scene: String,
+    pub campaign: String,
+    /// folder the inventory and poster icons are loaded from, empty for the
+    /// asset root
+    pub texture_folder: String,
+    /// JSON Lines file analytics are appended to on native builds, empty to
+    /// keep them in memory
+    pub analytics_log: String,
+    /// URL web builds send analytics to, `None` keeps them in the page
+    pub analytics_endpoint: Option<String>,
+    /// CSV file the player's path is appended to on native builds, empty to
+    /// keep it in memory
+    pub path_log: String,
+}
+
+impl Default for EscapeRoomConfig {
+    fn default() -> Self {
+        Self {
+            player_scene: "pl.gltf#Scene0".to_string(),
+            campaign: campaign::CAMPAIGN_PATH.to_string(),
+            texture_folder: String::new(),
+            analytics_log: "bevy_er_analytics.jsonl".to_string(),
+            analytics_endpoint: None,
+            path_log: "bevy_er_path.csv".to_string(),
+        }
+    }
+}
+
+impl EscapeRoomConfig {
+    fn texture(&self, file: &str) -> String {
+        if self.texture_folder.is_empty() {
+            file.to_string()
+        } else {
+            format!("{}/{}", self.texture_folder.trim_end_matches('/'), file)
         }
     }
 }
 
-#[wasm_bindgen]
+/// Picking things up, the equipped item and the inventory panel.
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Target>();
+        app.init_resource::<Equipped>();
+        app.init_resource::<Player>();
+        app.init_resource::<InventorySlots>();
+        app.add_plugin(DefaultRaycastingPlugin::<PickingRaycastSet>::default());
+        app.add_startup_system(load_assets.system());
+        app.add_system(ui_example.system().with_run_criteria(has_window.system()));
+        app.add_system(show_equipped.system());
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            update_pick_target.system().before(RaycastSystem::BuildRays),
+        );
+    }
+}
+
+/// The blacklight flashlight revealing the poster hints.
+pub struct BlacklightPlugin;
+
+impl Plugin for BlacklightPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<BlacklightTarget>();
+        app.add_plugin(DefaultRaycastingPlugin::<BlacklightRaycastSet>::default());
+        app.add_system(update_posters.system());
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            shine_on_poster.system().before(RaycastSystem::BuildRays),
+        );
+    }
+}
+
+/// The statue holders and checking them against the room's solution.
+pub struct StatuePlugin;
+
+impl Plugin for StatuePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PlaceTarget>();
+        app.init_resource::<StatueHolders>();
+        app.init_resource::<PuzzleState>();
+        app.init_resource::<PuzzleDefinition>();
+        app.add_event::<PuzzleAttempt>();
+        app.add_plugin(feedback::PuzzleFeedbackPlugin);
+        app.add_system(check_for_solution.system());
+        app.add_system(show_placed.system());
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            update_place_target
+                .system()
+                .before(RaycastSystem::BuildRays),
+        );
+    }
+}
+
+/// The puzzle door swinging open.
+pub struct DoorPlugin;
+
+impl Plugin for DoorPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<InsideDoorAngle>();
+        app.init_resource::<InsideDoorSwung>();
+        app.add_system(
+            swing_inside_door
+                .system()
+                .with_run_criteria(pause::not_paused.system()),
+        );
+    }
+}
+
+/// The whole game. Expects `DefaultPlugins` and `EguiPlugin` to have been
+/// added already; the MSAA sample count from `Settings` is only honoured if
+/// the host inserts it before `DefaultPlugins`, as `run` does.
+#[derive(Default)]
+pub struct EscapeRoomPlugin {
+    pub config: EscapeRoomConfig,
+    /// leaves out the flycam, which needs a window, opens no audio device and
+    /// saves nothing, neither settings and progress nor the analytics and path
+    /// logs, e.g. for the `replay` binary
+    pub headless: bool,
+}
+
+impl Plugin for EscapeRoomPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(StorageConfig {
+            saving: !self.headless,
+        });
+        if self.headless {
+            app.insert_resource(EscapeRoomConfig {
+                analytics_log: String::new(),
+                path_log: String::new(),
+                ..self.config.clone()
+            });
+        } else {
+            app.insert_resource(self.config.clone());
+            app.add_plugin(NoCameraPlayerPlugin);
+        }
+        app.add_plugin(GameTimePlugin);
+        app.add_plugin(SettingsPlugin);
+        app.add_plugin(InputBlockPlugin);
+        app.add_plugin(LoadingPlugin);
+        app.add_plugin(LocalizationPlugin);
+        app.add_plugin(SceneReadyPlugin);
+        app.add_plugin(GameAudioPlugin {
+            headless: self.headless,
+        });
+        app.add_plugin(InteractionPlugin);
+        app.add_plugin(InventoryPlugin);
+        app.add_plugin(BlacklightPlugin);
+        app.add_plugin(StatuePlugin);
+        app.add_plugin(DoorPlugin);
+        app.add_plugin(LightingPlugin);
+        app.add_plugin(CutscenePlugin);
+        app.add_plugin(RoomsPlugin);
+        app.add_plugin(CampaignPlugin);
+        app.add_plugin(PuzzleGraphPlugin);
+        app.add_plugin(ScriptingPlugin);
+        app.add_plugin(DebugOverlayPlugin);
+        app.add_plugin(ConsolePlugin);
+        app.add_plugin(RecordingPlugin);
+        app.add_plugin(AnalyticsPlugin);
+        app.add_plugin(HeatmapPlugin);
+        app.add_plugin(AchievementsPlugin);
+        app.add_plugin(AccessibilityPlugin);
+        app.add_plugin(SubtitlesPlugin);
+        app.add_plugin(TouchPlugin);
+        app.add_plugin(PausePlugin);
+        app.add_startup_system(crate::setup.system());
+        app.add_system(
+            rotator_system
+                .system()
+                .with_run_criteria(pause::not_paused.system()),
+        );
+        app.add_system(keyboard_input_system.system().label("keyboard_input"));
+        app.add_system(tag_stuff.system().label("tag_stuff"));
+    }
+}
+
+/// The native entry point. The web build starts from `browser::run`, which
+/// takes its settings from the host page.
+#[cfg(not(target_arch = "wasm32"))]
 pub fn run() {
     let mut app = App::build();
     #[cfg(feature = "bundle")]
@@ -894,49 +1288,24 @@ pub fn run() {
             asset_folder: "../Resources/assets".to_string(),
         });
     }
+    add_game(&mut app, EscapeRoomConfig::default());
+    app.run();
+}
+
+/// Adds bevy and the game, after any platform resources have been inserted.
+fn add_game(app: &mut AppBuilder, config: EscapeRoomConfig) {
+    // bevy builds its pipelines with the sample count it finds at startup
+    app.insert_resource(Msaa {
+        samples: Settings::load().msaa_samples,
+    });
     app.add_plugins(DefaultPlugins);
-    app.add_plugin(NoCameraPlayerPlugin);
     app.add_plugin(EguiPlugin);
-    app.init_resource::<Done>();
-    app.init_resource::<Target>();
-    app.init_resource::<BlacklightTarget>();
-    app.init_resource::<PlaceTarget>();
-    app.init_resource::<Equipped>();
-    app.init_resource::<EquippedInstance>();
-    app.init_resource::<Player>();
-    app.init_resource::<StatueHolders>();
-    app.init_resource::<PuzzleState>();
-    app.init_resource::<InsideDoorAngle>();
-    app.add_plugin(DefaultRaycastingPlugin::<PickingRaycastSet>::default());
-    app.add_plugin(DefaultRaycastingPlugin::<BlacklightRaycastSet>::default());
-    app.add_startup_system(load_assets.system());
-    app.add_startup_system(crate::setup.system());
-    app.add_system(rotator_system.system());
-    app.add_system(ui_example.system());
-    app.add_system(check_for_solution.system());
-    app.add_system(keyboard_input_system.system());
-    app.add_system(tag_stuff.system());
-    app.add_system(show_equipped.system());
-    app.add_system(show_placed.system());
-    app.add_system(update_posters.system());
-    app.add_system_to_stage(
-        CoreStage::PostUpdate,
-        update_pick_target.system().before(RaycastSystem::BuildRays),
-    );
-    app.add_system_to_stage(
-        CoreStage::PostUpdate,
-        update_place_target
-            .system()
-            .before(RaycastSystem::BuildRays),
-    );
-    app.add_system_to_stage(
-        CoreStage::PostUpdate,
-        shine_on_poster.system().before(RaycastSystem::BuildRays),
-    );
+    app.add_plugin(EscapeRoomPlugin {
+        config,
+        ..Default::default()
+    });
 
     // when building for Web, use WebGL2 rendering
     #[cfg(target_arch = "wasm32")]
     app.add_plugin(bevy_webgl2::WebGL2Plugin);
-
-    app.run();
 }
@@ -0,0 +1,318 @@
+//! Room lighting. The `SchoolLight*` fixtures from `er.gltf` get a point
+//! light each, driven by a `LightingMode` that changes with the puzzle stage,
+//! the wall switch (`Button`) and puzzle events. The `Lamp.NNN` nodes are
+//! Blender's default lamp duplicated without any light data, so they are
+//! ignored.
+
+use crate::{
+    game_time::GameTime, interaction::InteractionEvent, pause::not_paused, Player, PuzzleAttempt,
+    PuzzleState,
+};
+use bevy::{pbr::AmbientLight, prelude::*};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+const ROOM_LIGHT_INTENSITY: f32 = 300.0;
+const ROOM_LIGHT_RANGE: f32 = 25.0;
+const WRONG_ATTEMPT_FLICKER_SECONDS: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum LightingMode {
+    On,
+    Off,
+    /// room lights almost off with a violet ambient so the posters read
+    Blacklight,
+}
+
+impl LightingMode {
+    fn room_light_level(self) -> f32 {
+        match self {
+            LightingMode::On => 1.0,
+            LightingMode::Off => 0.0,
+            LightingMode::Blacklight => 0.04,
+        }
+    }
+
+    fn ambient(self) -> AmbientLight {
+        match self {
+            LightingMode::On => AmbientLight {
+                color: Color::WHITE,
+                brightness: 0.1,
+            },
+            LightingMode::Off => AmbientLight {
+                color: Color::WHITE,
+                brightness: 0.01,
+            },
+            LightingMode::Blacklight => AmbientLight {
+                color: Color::rgb(0.35, 0.1, 1.0),
+                brightness: 0.03,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PuzzleStage {
+    Searching,
+    FlashlightFound,
+    Solved,
+}
+
+impl PuzzleStage {
+    fn current(player: &Player, puzzle_state: &PuzzleState) -> Self {
+        if puzzle_state.0 {
+            PuzzleStage::Solved
+        } else if player.inventory.contains("InvBlacklightFlashlight") {
+            PuzzleStage::FlashlightFound
+        } else {
+            PuzzleStage::Searching
+        }
+    }
+}
+
+/// Lighting for one puzzle stage: the mode set on entering the stage and the
+/// mode the wall switch flips to from `On`.
+#[derive(Debug, Clone, Copy)]
+pub struct StageLighting {
+    pub initial: LightingMode,
+    pub switched: LightingMode,
+}
+
+pub struct LightingStages(pub HashMap<PuzzleStage, StageLighting>);
+
+impl Default for LightingStages {
+    fn default() -> Self {
+        let mut stages = HashMap::new();
+        stages.insert(
+            PuzzleStage::Searching,
+            StageLighting {
+                initial: LightingMode::On,
+                switched: LightingMode::Off,
+            },
+        );
+        stages.insert(
+            PuzzleStage::FlashlightFound,
+            StageLighting {
+                initial: LightingMode::On,
+                switched: LightingMode::Blacklight,
+            },
+        );
+        stages.insert(
+            PuzzleStage::Solved,
+            StageLighting {
+                initial: LightingMode::On,
+                switched: LightingMode::Off,
+            },
+        );
+        Self(stages)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum LightingCommand {
+    Set(LightingMode),
+    /// what the wall switch does, flips between `On` and the stage's
+    /// switched mode
+    Toggle,
+    Flicker(f32),
+}
+
+pub struct LightingController {
+    pub mode: LightingMode,
+    pub stage: Option<PuzzleStage>,
+    flicker: Option<Timer>,
+}
+
+impl Default for LightingController {
+    fn default() -> Self {
+        Self {
+            mode: LightingMode::On,
+            stage: None,
+            flicker: None,
+        }
+    }
+}
+
+impl LightingController {
+    pub fn is_flickering(&self) -> bool {
+        self.flicker.is_some()
+    }
+}
+
+pub struct RoomLight;
+
+/// The lights are children of their fixture so they leave with the room.
+/// Their names start with the fixture's, so they're kept out of the query.
+fn spawn_room_lights(
+    mut commands: Commands,
+    fixtures: Query<(Entity, &Name, &Transform), (Added<Name>, Without<RoomLight>)>,
+) {
+    for (entity, name, transform) in fixtures.iter() {
+        if name.as_str().starts_with("SchoolLight") {
+            // half a unit below the fixture, in the fixture's rotated frame
+            let below = transform.rotation.inverse() * Vec3::new(0.0, -0.5, 0.0);
+            let light_name = format!("{}Light", name.as_str());
+            commands.entity(entity).with_children(|parent| {
+                parent
+                    .spawn_bundle(LightBundle {
+                        light: Light {
+                            intensity: ROOM_LIGHT_INTENSITY,
+                            range: ROOM_LIGHT_RANGE,
+                            ..Light::default()
+                        },
+                        transform: Transform::from_translation(below),
+                        ..Default::default()
+                    })
+                    .insert(RoomLight)
+                    .insert(Name::new(light_name));
+            });
+        }
+    }
+}
+
+fn follow_puzzle_stage(
+    player: Res<Player>,
+    puzzle_state: Res<PuzzleState>,
+    stages: Res<LightingStages>,
+    mut controller: ResMut<LightingController>,
+) {
+    let stage = PuzzleStage::current(&player, &puzzle_state);
+    if controller.stage != Some(stage) {
+        controller.stage = Some(stage);
+        if let Some(lighting) = stages.0.get(&stage) {
+            controller.mode = lighting.initial;
+        }
+    }
+}
+
+fn handle_lighting_commands(
+    mut lighting_commands: EventReader<LightingCommand>,
+    mut attempts: EventReader<PuzzleAttempt>,
+    stages: Res<LightingStages>,
+    mut controller: ResMut<LightingController>,
+) {
+    for attempt in attempts.iter() {
+        if !attempt.correct {
+            controller.flicker = Some(Timer::from_seconds(WRONG_ATTEMPT_FLICKER_SECONDS, false));
+        }
+    }
+    for command in lighting_commands.iter() {
+        match *command {
+            LightingCommand::Set(mode) => controller.mode = mode,
+            LightingCommand::Toggle => {
+                let dark = controller
+                    .stage
+                    .and_then(|stage| stages.0.get(&stage))
+                    .map(|lighting| lighting.switched)
+                    .unwrap_or(LightingMode::Off);
+                controller.mode = if controller.mode == LightingMode::On {
+                    dark
+                } else {
+                    LightingMode::On
+                };
+            }
+            LightingCommand::Flicker(seconds) => {
+                controller.flicker = Some(Timer::from_seconds(seconds, false))
+            }
+        }
+    }
+}
+
+fn use_wall_switch(
+    mut interactions: EventReader<InteractionEvent>,
+    mut lighting_commands: EventWriter<LightingCommand>,
+) {
+    for interaction in interactions.iter() {
+        if let InteractionEvent::Use { name, .. } = interaction {
+            if name == "Button" {
+                lighting_commands.send(LightingCommand::Toggle);
+            }
+        }
+    }
+}
+
+/// cheap deterministic noise in 0..1, good enough for a failing tube light
+fn flicker_noise(t: f32) -> f32 {
+    ((t * 12.9898).sin() * 43758.547).fract().abs()
+}
+
+fn apply_lighting(
+    time: Res<GameTime>,
+    mut controller: ResMut<LightingController>,
+    mut ambient: ResMut<AmbientLight>,
+    mut lights: Query<&mut Light, With<RoomLight>>,
+) {
+    let mut level = controller.mode.room_light_level();
+    if let Some(timer) = controller.flicker.as_mut() {
+        timer.tick(time.delta());
+        if timer.finished() {
+            controller.flicker = None;
+        } else {
+            // quantize time so the flicker changes at a visible rate
+            let step = (time.seconds_since_startup() * 20.0).floor() as f32;
+            level = if flicker_noise(step) > 0.5 { 1.0 } else { 0.1 };
+        }
+    }
+
+    for mut light in lights.iter_mut() {
+        light.intensity = ROOM_LIGHT_INTENSITY * level;
+    }
+    *ambient = controller.mode.ambient();
+}
+
+pub struct LightingPlugin;
+
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<LightingCommand>();
+        app.init_resource::<LightingController>();
+        app.init_resource::<LightingStages>();
+        app.add_system(spawn_room_lights.system());
+        app.add_system(use_wall_switch.system().before("handle_lighting_commands"));
+        app.add_system(follow_puzzle_stage.system().label("follow_puzzle_stage"));
+        app.add_system(
+            handle_lighting_commands
+                .system()
+                .label("handle_lighting_commands")
+                .after("follow_puzzle_stage"),
+        );
+        app.add_system(
+            apply_lighting
+                .system()
+                .with_run_criteria(not_paused.system())
+                .after("handle_lighting_commands"),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_fixture_gets_one_light() {
+        let mut world = World::default();
+        for name in ["SchoolLight", "SchoolLight.001", "Blackboard"].iter() {
+            world
+                .spawn()
+                .insert(Name::new(name.to_string()))
+                .insert(Transform::identity());
+        }
+        let mut stage = SystemStage::parallel();
+        stage.add_system(spawn_room_lights.system());
+        stage.run(&mut world);
+        stage.run(&mut world);
+
+        let lights = world
+            .query_filtered::<&Parent, With<RoomLight>>()
+            .iter(&world)
+            .map(|parent| parent.0)
+            .collect::<Vec<_>>();
+        assert_eq!(lights.len(), 2);
+        for fixture in lights {
+            let name = world.get::<Name>(fixture).unwrap();
+            assert!(name.as_str().starts_with("SchoolLight"));
+            assert!(world.get::<RoomLight>(fixture).is_none());
+        }
+    }
+}
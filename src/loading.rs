@@ -0,0 +1,117 @@
+//! Loading screen. Everything loaded up front registers its handle in
+//! `LoadingAssets`; until the whole group has loaded a progress bar covers
+//! the canvas, gameplay input is blocked and the main menu stays hidden.
+
+use crate::{has_window, input::InputBlock, localization::Localization, rooms::Rooms};
+use bevy::{asset::LoadState, prelude::*};
+use bevy_egui::{egui, EguiContext};
+use fluent_bundle::FluentArgs;
+
+#[derive(Default)]
+pub struct LoadingAssets {
+    handles: Vec<(String, HandleUntyped)>,
+    loaded: usize,
+    failed: Vec<String>,
+    done: bool,
+}
+
+impl LoadingAssets {
+    pub fn add(&mut self, path: &str, handle: HandleUntyped) {
+        self.handles.push((path.to_string(), handle));
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    pub fn progress(&self) -> f32 {
+        if self.handles.is_empty() {
+            0.0
+        } else {
+            self.loaded as f32 / self.handles.len() as f32
+        }
+    }
+}
+
+/// The room list only arrives with the campaign manifest, which adds the
+/// room scenes to the group, so wait for that too.
+fn track_loading(
+    asset_server: Res<AssetServer>,
+    rooms: Res<Rooms>,
+    mut loading: ResMut<LoadingAssets>,
+    mut input_block: ResMut<InputBlock>,
+) {
+    if loading.done {
+        return;
+    }
+
+    let group = loading.handles.iter().map(|(_, handle)| handle.id);
+    let state = asset_server.get_group_load_state(group);
+    let loading = &mut *loading;
+    loading.loaded = 0;
+    loading.failed.clear();
+    for (path, handle) in loading.handles.iter() {
+        match asset_server.get_load_state(handle) {
+            LoadState::Loaded => loading.loaded += 1,
+            LoadState::Failed => loading.failed.push(path.clone()),
+            _ => {}
+        }
+    }
+    loading.done = state == LoadState::Loaded && !rooms.definitions.is_empty();
+    input_block.set("loading", !loading.done);
+}
+
+/// The Fluent files load along with everything else, so every string here
+/// has an English default.
+fn loading_screen(
+    egui_context: Res<EguiContext>,
+    loading: Res<LoadingAssets>,
+    localization: Res<Localization>,
+) {
+    if loading.done {
+        return;
+    }
+
+    let ctx = egui_context.ctx();
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Background,
+        egui::Id::new("loading_background"),
+    ));
+    painter.rect_filled(ctx.input().screen_rect(), 0.0, egui::Color32::BLACK);
+    egui::Window::new(localization.tr_or("loading-title", "Loading"))
+        .id(egui::Id::new("loading"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.add(egui::ProgressBar::new(loading.progress()).show_percentage());
+            let mut args = FluentArgs::new();
+            args.set("loaded", loading.loaded);
+            args.set("total", loading.handles.len());
+            let progress = format!("{} of {} assets", loading.loaded, loading.handles.len());
+            let progress = localization.tr_args_or("loading-progress", &args, &progress);
+            ui.label(progress);
+            if !loading.failed.is_empty() {
+                ui.separator();
+                let failed = localization.tr_or("loading-failed", "These assets failed to load:");
+                ui.colored_label(egui::Color32::RED, failed);
+                for path in loading.failed.iter() {
+                    ui.colored_label(egui::Color32::RED, path.as_str());
+                }
+            }
+        });
+}
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<LoadingAssets>();
+        app.add_system(track_loading.system());
+        app.add_system(
+            loading_screen
+                .system()
+                .with_run_criteria(has_window.system()),
+        );
+    }
+}
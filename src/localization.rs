@@ -0,0 +1,185 @@
+//! Player facing text, looked up by key in Fluent files under
+//! `locales/<language>/main.ftl`. The language comes from `Settings`; keys
+//! missing from it fall back to English, and keys missing from English show
+//! as the key itself so they are easy to spot.
+//!
+//! Rooms and achievements come from the campaign manifest, so their text is
+//! looked up as `room-<id>`, `achievement-<id>` and
+//! `achievement-<id>-description`, falling back to what the manifest says.
+
+use crate::{loading::LoadingAssets, settings::Settings};
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use fluent_bundle::{concurrent::FluentBundle, FluentArgs, FluentResource};
+use std::collections::HashMap;
+use unic_langid::LanguageIdentifier;
+
+pub const FALLBACK_LANGUAGE: &str = "en-US";
+
+/// language id and the name it is listed under in the settings
+pub const LANGUAGES: &[(&str, &str)] = &[("en-US", "English"), ("de", "Deutsch")];
+
+#[derive(Debug, TypeUuid)]
+#[uuid = "b0d7c3a2-5e41-4c8f-9a36-1f2e8d4b7c90"]
+pub struct FluentSource(pub String);
+
+#[derive(Default)]
+pub struct FluentLoader;
+
+impl AssetLoader for FluentLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let source = String::from_utf8(bytes.to_vec())?;
+            load_context.set_default_asset(LoadedAsset::new(FluentSource(source)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ftl"]
+    }
+}
+
+#[derive(Default)]
+pub struct Localization {
+    handles: HashMap<String, Handle<FluentSource>>,
+    /// language `bundle` was built for
+    language: String,
+    bundle: Option<FluentBundle<FluentResource>>,
+    fallback: Option<FluentBundle<FluentResource>>,
+}
+
+impl Localization {
+    fn format(&self, key: &str, args: Option<&FluentArgs>) -> Option<String> {
+        self.bundle
+            .iter()
+            .chain(self.fallback.iter())
+            .find_map(|bundle| {
+                let pattern = bundle.get_message(key)?.value()?;
+                let mut errors = Vec::new();
+                let text = bundle.format_pattern(pattern, args, &mut errors);
+                for err in errors {
+                    warn!("{}: {}", key, err);
+                }
+                Some(text.into_owned())
+            })
+    }
+
+    pub fn tr(&self, key: &str) -> String {
+        self.format(key, None).unwrap_or_else(|| key.to_string())
+    }
+
+    pub fn tr_args(&self, key: &str, args: &FluentArgs) -> String {
+        self.format(key, Some(args))
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    /// For text that has a sensible default elsewhere, like a title from the
+    /// campaign manifest, or that is needed before the Fluent files load.
+    pub fn tr_or(&self, key: &str, default: &str) -> String {
+        self.format(key, None)
+            .unwrap_or_else(|| default.to_string())
+    }
+
+    pub fn tr_args_or(&self, key: &str, args: &FluentArgs, default: &str) -> String {
+        self.format(key, Some(args))
+            .unwrap_or_else(|| default.to_string())
+    }
+}
+
+fn locale_path(language: &str) -> String {
+    format!("locales/{}/main.ftl", language)
+}
+
+fn load_locales(
+    asset_server: Res<AssetServer>,
+    mut localization: ResMut<Localization>,
+    mut loading: ResMut<LoadingAssets>,
+) {
+    for (language, _) in LANGUAGES.iter() {
+        let path = locale_path(language);
+        let handle: Handle<FluentSource> = asset_server.load(path.as_str());
+        loading.add(&path, handle.clone_untyped());
+        localization.handles.insert(language.to_string(), handle);
+    }
+}
+
+fn build_bundle(language: &str, source: &FluentSource) -> Option<FluentBundle<FluentResource>> {
+    let id: LanguageIdentifier = match language.parse() {
+        Ok(id) => id,
+        Err(err) => {
+            error!("{} is not a language id: {}", language, err);
+            return None;
+        }
+    };
+    let resource = match FluentResource::try_new(source.0.clone()) {
+        Ok(resource) => resource,
+        Err((resource, errors)) => {
+            for err in errors {
+                error!("{}: {:?}", locale_path(language), err);
+            }
+            resource
+        }
+    };
+    let mut bundle = FluentBundle::new_concurrent(vec![id]);
+    // egui shows the isolation marks around arguments as boxes
+    bundle.set_use_isolating(false);
+    if let Err(errors) = bundle.add_resource(resource) {
+        for err in errors {
+            error!("{}: {}", locale_path(language), err);
+        }
+    }
+    Some(bundle)
+}
+
+/// Rebuilds the bundles when a Fluent file (re)loads or the language
+/// setting changes.
+fn apply_locales(
+    mut asset_events: EventReader<AssetEvent<FluentSource>>,
+    sources: Res<Assets<FluentSource>>,
+    settings: Res<Settings>,
+    mut localization: ResMut<Localization>,
+) {
+    let reloaded = asset_events.iter().count() > 0;
+    if !reloaded && localization.language == settings.language {
+        return;
+    }
+
+    let source = |language: &str| {
+        localization
+            .handles
+            .get(language)
+            .and_then(|handle| sources.get(handle))
+    };
+    let fallback =
+        source(FALLBACK_LANGUAGE).and_then(|source| build_bundle(FALLBACK_LANGUAGE, source));
+    let bundle = if settings.language == FALLBACK_LANGUAGE {
+        None
+    } else {
+        source(&settings.language).and_then(|source| build_bundle(&settings.language, source))
+    };
+    localization.fallback = fallback;
+    localization.bundle = bundle;
+    localization.language = settings.language.clone();
+}
+
+/// Loads the Fluent files and keeps `Localization` on the chosen language.
+pub struct LocalizationPlugin;
+
+impl Plugin for LocalizationPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<FluentSource>();
+        app.init_asset_loader::<FluentLoader>();
+        app.init_resource::<Localization>();
+        app.add_startup_system(load_locales.system());
+        app.add_system(apply_locales.system());
+    }
+}
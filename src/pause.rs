@@ -0,0 +1,77 @@
+//! Pausing. The game pauses when its window loses focus, and in a browser
+//! also when the page is hidden or the host page calls `pause()`. While
+//! paused gameplay input is blocked, sound is silenced, `GameTime` stands
+//! still and systems run with `not_paused` are skipped, until the Resume
+//! button or the host page's `resume()`. Since room timers read `GameTime`,
+//! time spent paused doesn't count towards solve times.
+
+use crate::{audio::AudioMixer, has_window, input::InputBlock, localization::Localization};
+use bevy::{ecs::schedule::ShouldRun, prelude::*, window::WindowFocused};
+use bevy_egui::{egui, EguiContext};
+
+#[derive(Debug, Default)]
+pub struct Paused(pub bool);
+
+/// Run criterion for gameplay systems that should stand still while paused.
+pub(crate) fn not_paused(paused: Res<Paused>) -> ShouldRun {
+    if paused.0 {
+        ShouldRun::No
+    } else {
+        ShouldRun::Yes
+    }
+}
+
+fn pause_on_focus_loss(mut focus: EventReader<WindowFocused>, mut paused: ResMut<Paused>) {
+    if focus.iter().any(|event| !event.focused) {
+        paused.0 = true;
+    }
+}
+
+fn apply_pause(
+    paused: Res<Paused>,
+    mut input_block: ResMut<InputBlock>,
+    mut mixer: ResMut<AudioMixer>,
+) {
+    if paused.is_changed() {
+        input_block.set("paused", paused.0);
+        mixer.paused = paused.0;
+    }
+}
+
+fn pause_menu(
+    egui_context: Res<EguiContext>,
+    localization: Res<Localization>,
+    mut paused: ResMut<Paused>,
+) {
+    if !paused.0 {
+        return;
+    }
+
+    egui::Window::new(localization.tr("pause-title"))
+        .id(egui::Id::new("pause"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(egui_context.ctx(), |ui| {
+            if ui.button(localization.tr("pause-resume")).clicked() {
+                paused.0 = false;
+            }
+        });
+}
+
+/// Pauses on focus loss, with a Resume button.
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Paused>();
+        app.add_system(pause_on_focus_loss.system().label("pause"));
+        app.add_system(
+            pause_menu
+                .system()
+                .with_run_criteria(has_window.system())
+                .label("pause"),
+        );
+        app.add_system(apply_pause.system().after("pause"));
+    }
+}
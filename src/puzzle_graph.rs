@@ -0,0 +1,300 @@
+//! The puzzle flow as a graph of goals. A goal completes once all of its
+//! prerequisites have and its condition holds, which fires `GoalCompleted`
+//! and applies the goal's world changes. Each room lists its goals in the
+//! campaign manifest; the graph and its progress belong to the current room
+//! and are swapped along with the rest of the room state. The scene has no
+//! keypad yet; one can be gated the same way once it has been modelled.
+
+use crate::{
+    game_time::GameTime,
+    has_window,
+    lighting::{LightingCommand, LightingMode},
+    pause::not_paused,
+    BlacklightTarget, PlaySound, Player, PuzzleState, SoundEffect,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use serde::Deserialize;
+use std::collections::HashSet;
+
+/// how fast nodes slide open, in units per second
+const SLIDE_SPEED: f32 = 1.0;
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum GoalCondition {
+    /// the item is in the player's inventory
+    Item(String),
+    /// this many different posters have been lit with the blacklight
+    PostersRevealed(usize),
+    StatuesSolved,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum WorldChange {
+    /// moves a scene node by an offset, e.g. to slide a cabinet open
+    Slide {
+        node: String,
+        by: Vec3,
+    },
+    Lighting(LightingMode),
+    Sound(SoundEffect),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Goal {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub requires: Vec<String>,
+    pub condition: GoalCondition,
+    #[serde(default)]
+    pub unlocks: Vec<WorldChange>,
+}
+
+/// The goals of the current room.
+#[derive(Default)]
+pub struct PuzzleGraph {
+    pub goals: Vec<Goal>,
+}
+
+impl PuzzleGraph {
+    pub fn get(&self, id: &str) -> Option<&Goal> {
+        self.goals.iter().find(|goal| goal.id == id)
+    }
+}
+
+/// Goals completed and posters seen in the current room.
+#[derive(Debug, Default, Clone)]
+pub struct GoalProgress {
+    pub completed: HashSet<String>,
+    pub revealed_posters: HashSet<String>,
+}
+
+impl GoalProgress {
+    fn is_ready(&self, goal: &Goal) -> bool {
+        goal.requires.iter().all(|id| self.completed.contains(id))
+    }
+}
+
+pub struct GoalCompleted(pub String);
+
+/// Marks a node that slides open, with where it started.
+struct ClosedPosition(Vec3);
+
+#[derive(Default)]
+pub struct PuzzleGraphUi {
+    pub open: bool,
+}
+
+fn record_revealed_posters(
+    player: Res<Player>,
+    target: Res<BlacklightTarget>,
+    mut progress: ResMut<GoalProgress>,
+) {
+    if player.equipped_name() != "InvBlacklightFlashlight" {
+        return;
+    }
+    if let Some(poster) = target.0.as_ref() {
+        if !progress.revealed_posters.contains(&poster.name) {
+            progress.revealed_posters.insert(poster.name.clone());
+        }
+    }
+}
+
+fn condition_holds(
+    condition: &GoalCondition,
+    player: &Player,
+    puzzle_state: &PuzzleState,
+    progress: &GoalProgress,
+) -> bool {
+    match condition {
+        GoalCondition::Item(name) => player.inventory.contains(name),
+        GoalCondition::PostersRevealed(count) => progress.revealed_posters.len() >= *count,
+        GoalCondition::StatuesSolved => puzzle_state.0,
+    }
+}
+
+fn advance_puzzle_graph(
+    graph: Res<PuzzleGraph>,
+    player: Res<Player>,
+    puzzle_state: Res<PuzzleState>,
+    mut progress: ResMut<GoalProgress>,
+    mut completed: EventWriter<GoalCompleted>,
+) {
+    // goals can be listed in any order, so keep going until nothing changes
+    loop {
+        let next = graph.goals.iter().find(|goal| {
+            !progress.completed.contains(&goal.id)
+                && progress.is_ready(goal)
+                && condition_holds(&goal.condition, &player, &puzzle_state, &progress)
+        });
+        match next {
+            Some(goal) => {
+                progress.completed.insert(goal.id.clone());
+                completed.send(GoalCompleted(goal.id.clone()));
+            }
+            None => break,
+        }
+    }
+}
+
+fn apply_goal_unlocks(
+    graph: Res<PuzzleGraph>,
+    mut completed: EventReader<GoalCompleted>,
+    mut lighting_commands: EventWriter<LightingCommand>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    for GoalCompleted(id) in completed.iter() {
+        let goal = match graph.get(id) {
+            Some(goal) => goal,
+            None => continue,
+        };
+        for change in goal.unlocks.iter() {
+            match change {
+                // slides follow the progress so they survive leaving the room
+                WorldChange::Slide { .. } => {}
+                WorldChange::Lighting(mode) => lighting_commands.send(LightingCommand::Set(*mode)),
+                WorldChange::Sound(effect) => sounds.send(PlaySound::at_listener(*effect)),
+            }
+        }
+    }
+}
+
+fn mark_sliding_nodes(
+    mut commands: Commands,
+    graph: Res<PuzzleGraph>,
+    nodes: Query<(Entity, &Name, &Transform), Added<Name>>,
+) {
+    for (entity, name, transform) in nodes.iter() {
+        let slides = graph
+            .goals
+            .iter()
+            .flat_map(|goal| goal.unlocks.iter())
+            .any(|change| match change {
+                WorldChange::Slide { node, .. } => node == name.as_str(),
+                _ => false,
+            });
+        if slides {
+            commands
+                .entity(entity)
+                .insert(ClosedPosition(transform.translation));
+        }
+    }
+}
+
+fn slide_unlocked_nodes(
+    time: Res<GameTime>,
+    graph: Res<PuzzleGraph>,
+    progress: Res<GoalProgress>,
+    mut nodes: Query<(&Name, &ClosedPosition, &mut Transform)>,
+) {
+    for (name, closed, mut transform) in nodes.iter_mut() {
+        let mut target = closed.0;
+        for goal in graph.goals.iter() {
+            if !progress.completed.contains(&goal.id) {
+                continue;
+            }
+            for change in goal.unlocks.iter() {
+                if let WorldChange::Slide { node, by } = change {
+                    if node == name.as_str() {
+                        target += *by;
+                    }
+                }
+            }
+        }
+        // only write once there is somewhere to go, so the node doesn't
+        // show up as changed every frame
+        if transform.translation == target {
+            continue;
+        }
+        let remaining = target - transform.translation;
+        let step = SLIDE_SPEED * time.delta_seconds();
+        transform.translation = if remaining.length() <= step {
+            target
+        } else {
+            transform.translation + remaining.normalize() * step
+        };
+    }
+}
+
+fn toggle_puzzle_graph_ui(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut graph_ui: ResMut<PuzzleGraphUi>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F5) {
+        graph_ui.open = !graph_ui.open;
+    }
+}
+
+fn puzzle_graph_ui(
+    egui_context: Res<EguiContext>,
+    mut graph_ui: ResMut<PuzzleGraphUi>,
+    graph: Res<PuzzleGraph>,
+    progress: Res<GoalProgress>,
+) {
+    if !graph_ui.open {
+        return;
+    }
+
+    egui::Window::new("Puzzle graph")
+        .open(&mut graph_ui.open)
+        .default_width(300.0)
+        .show(egui_context.ctx(), |ui| {
+            egui::Grid::new("goals").striped(true).show(ui, |ui| {
+                for goal in graph.goals.iter() {
+                    let status = if progress.completed.contains(&goal.id) {
+                        "done"
+                    } else if progress.is_ready(goal) {
+                        "ready"
+                    } else {
+                        "blocked"
+                    };
+                    ui.label(status);
+                    ui.label(goal.title.as_str())
+                        .on_hover_text(format!("{:?}", goal.condition));
+                    ui.label(goal.requires.join(", "));
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+            let mut posters: Vec<_> = progress.revealed_posters.iter().cloned().collect();
+            posters.sort();
+            ui.label(format!("Posters revealed: {}", posters.join(", ")));
+        });
+}
+
+/// Tracks puzzle goals and their prerequisites, F5 shows the graph.
+pub struct PuzzleGraphPlugin;
+
+impl Plugin for PuzzleGraphPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<GoalCompleted>();
+        app.init_resource::<PuzzleGraph>();
+        app.init_resource::<GoalProgress>();
+        app.init_resource::<PuzzleGraphUi>();
+        app.add_system(
+            record_revealed_posters
+                .system()
+                .label("record_revealed_posters"),
+        );
+        app.add_system(
+            advance_puzzle_graph
+                .system()
+                .label("advance_puzzle_graph")
+                .after("record_revealed_posters"),
+        );
+        app.add_system(apply_goal_unlocks.system().after("advance_puzzle_graph"));
+        app.add_system(mark_sliding_nodes.system());
+        app.add_system(
+            slide_unlocked_nodes
+                .system()
+                .with_run_criteria(not_paused.system()),
+        );
+        app.add_system(toggle_puzzle_graph_ui.system());
+        app.add_system(
+            puzzle_graph_ui
+                .system()
+                .with_run_criteria(has_window.system()),
+        );
+    }
+}
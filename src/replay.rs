@@ -0,0 +1,415 @@
+//! Input recording and replay, for reproducing playtesters' bugs.
+//!
+//! Every frame after a room is picked from the main menu is recorded: the
+//! frame delta, the keys pressed and released, mouse motion, console
+//! commands and where the camera ended up. F6 saves the recording so far
+//! under `bevy_er_recording` (a `.ron` file natively, `localStorage` on the
+//! web), and so does quitting. Picking another room from the menu starts a
+//! new recording, since clicks on the menu itself are not recorded.
+//!
+//! The `replay` binary plays a recording back headless, starting from the
+//! inventory, statues and goals the room had when the recording began. Keys
+//! and mouse motion are fed in ahead of `keyboard_input_system` and the
+//! flycam, if there is one, and the camera is then held to the recorded path
+//! so picking sees exactly what the player saw. `GameTime` is moved on by the
+//! recorded frame deltas rather than the wall clock, so timers and animations
+//! play out the same however fast the replay runs. At the end it reports the
+//! final `PuzzleState`.
+
+use crate::{
+    campaign::{CampaignProgress, MainMenu},
+    console::ConsoleCommand,
+    game_time::GameTime,
+    loading::LoadingAssets,
+    puzzle_graph::GoalProgress,
+    rooms::EnterRoom,
+    scene_ready::{SceneKind, SceneReady},
+    storage::{self, StorageConfig},
+    Location, Player, PuzzleState, StatueColor, StatueHolders,
+};
+use bevy::{
+    app::AppExit,
+    input::{mouse::MouseMotion, InputSystem},
+    prelude::*,
+};
+use bevy_flycam::FlyCam;
+use bevy_mod_raycast::RaycastSystem;
+use serde::{Deserialize, Serialize};
+
+const RECORDING_KEY: &str = "bevy_er_recording";
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// seconds since the previous frame
+    pub delta: f32,
+    pub pressed: Vec<KeyCode>,
+    pub released: Vec<KeyCode>,
+    pub mouse_motion: Vec<Vec2>,
+    pub console: Vec<ConsoleCommand>,
+    /// camera translation and rotation at the end of the frame
+    pub camera: (Vec3, Quat),
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub room: String,
+    pub completed_rooms: Vec<String>,
+    pub inventory: Vec<String>,
+    pub equipped: usize,
+    pub held_statues: Vec<(Location, StatueColor)>,
+    pub solved: bool,
+    pub completed_goals: Vec<String>,
+    pub revealed_posters: Vec<String>,
+    pub frames: Vec<RecordedFrame>,
+}
+
+/// Records the session unless a replay is running.
+pub struct Recorder {
+    enabled: bool,
+    recording: Option<Recording>,
+    /// room picked from the menu whose scene is still spawning
+    starting: Option<String>,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            recording: None,
+            starting: None,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start_recording(
+    mut recorder: ResMut<Recorder>,
+    menu: Res<MainMenu>,
+    mut menu_was_open: Local<bool>,
+    mut entered: EventReader<EnterRoom>,
+    mut scenes_ready: EventReader<SceneReady>,
+    progress: Res<CampaignProgress>,
+    player: Res<Player>,
+    statue_holders: Res<StatueHolders>,
+    puzzle_state: Res<PuzzleState>,
+    goals: Res<GoalProgress>,
+) {
+    // rooms entered through doors carry on with the same recording
+    for event in entered.iter() {
+        if *menu_was_open && recorder.enabled {
+            recorder.recording = None;
+            recorder.starting = Some(event.room.clone());
+        }
+    }
+    *menu_was_open = menu.open;
+
+    for ready in scenes_ready.iter() {
+        if let SceneKind::Room(room) = &ready.kind {
+            if recorder.starting.as_ref() != Some(room) {
+                continue;
+            }
+            recorder.starting = None;
+            let mut held_statues: Vec<_> = statue_holders
+                .held_statues
+                .iter()
+                .map(|(location, color)| (*location, *color))
+                .collect();
+            held_statues.sort_by_key(|(location, _)| *location as u8);
+            recorder.recording = Some(Recording {
+                room: room.clone(),
+                completed_rooms: progress.completed.iter().cloned().collect(),
+                inventory: player.inventory.iter().cloned().collect(),
+                equipped: player.equipped,
+                held_statues,
+                solved: puzzle_state.0,
+                completed_goals: goals.completed.iter().cloned().collect(),
+                revealed_posters: goals.revealed_posters.iter().cloned().collect(),
+                frames: Vec::new(),
+            });
+        }
+    }
+}
+
+fn record_input(
+    mut recorder: ResMut<Recorder>,
+    time: Res<GameTime>,
+    keys: Res<Input<KeyCode>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+) {
+    let recording = match recorder.recording.as_mut() {
+        Some(recording) => recording,
+        None => return,
+    };
+    recording.frames.push(RecordedFrame {
+        delta: time.delta_seconds(),
+        pressed: keys.get_just_pressed().copied().collect(),
+        released: keys.get_just_released().copied().collect(),
+        mouse_motion: mouse_motion.iter().map(|motion| motion.delta).collect(),
+        ..Default::default()
+    });
+}
+
+fn record_frame_end(
+    mut recorder: ResMut<Recorder>,
+    mut commands: EventReader<ConsoleCommand>,
+    camera: Query<&Transform, With<FlyCam>>,
+) {
+    let frame = match recorder
+        .recording
+        .as_mut()
+        .and_then(|recording| recording.frames.last_mut())
+    {
+        Some(frame) => frame,
+        None => return,
+    };
+    frame.console.extend(commands.iter().cloned());
+    if let Some(transform) = camera.iter().next() {
+        frame.camera = (transform.translation, transform.rotation);
+    }
+}
+
+/// Saves on F6 and on the way out, so a session that ends without F6 can
+/// still be replayed.
+fn save_recording(
+    storage_config: Res<StorageConfig>,
+    keyboard_input: Res<Input<KeyCode>>,
+    recorder: Res<Recorder>,
+    mut exit: EventReader<AppExit>,
+) {
+    let exiting = exit.iter().next().is_some();
+    if !keyboard_input.just_pressed(KeyCode::F6) && !exiting {
+        return;
+    }
+    match recorder.recording.as_ref() {
+        Some(recording) => {
+            storage::save(&storage_config, RECORDING_KEY, recording);
+            info!(
+                "saved {} frames in {} as {}",
+                recording.frames.len(),
+                recording.room,
+                RECORDING_KEY
+            );
+        }
+        None if !exiting => info!("nothing recorded yet, pick a room from the menu first"),
+        None => {}
+    }
+}
+
+/// Records every session, F6 or quitting saves the recording.
+pub struct RecordingPlugin;
+
+impl Plugin for RecordingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<StorageConfig>();
+        app.init_resource::<Recorder>();
+        app.add_system(start_recording.system());
+        // after the frame's `AppExit`, the runner quits once the frame is done
+        app.add_system_to_stage(CoreStage::Last, save_recording.system());
+        app.add_system_to_stage(
+            CoreStage::PreUpdate,
+            record_input
+                .system()
+                .after(InputSystem)
+                .after("touch_input")
+                .before("swallow_typed_keys"),
+        );
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            record_frame_end.system().before(RaycastSystem::BuildRays),
+        );
+    }
+}
+
+/// A recording being played back.
+pub struct Replay {
+    recording: Recording,
+    started: bool,
+    /// index of the next frame to play, once the room is ready
+    next: Option<usize>,
+    /// index of the frame being played
+    playing: Option<usize>,
+    keys: Input<KeyCode>,
+    /// furthest the flycam strayed from the recorded camera path
+    pub drift: f32,
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            started: false,
+            next: None,
+            playing: None,
+            keys: Input::default(),
+            drift: 0.0,
+        }
+    }
+
+    /// The frame being played.
+    pub fn current(&self) -> Option<&RecordedFrame> {
+        self.playing
+            .and_then(|index| self.recording.frames.get(index))
+    }
+}
+
+fn start_replay(
+    mut replay: ResMut<Replay>,
+    loading: Res<LoadingAssets>,
+    mut menu: ResMut<MainMenu>,
+    mut enter_room: EventWriter<EnterRoom>,
+) {
+    if replay.started || !loading.is_done() {
+        return;
+    }
+    replay.started = true;
+    menu.open = false;
+    enter_room.send(EnterRoom {
+        room: replay.recording.room.clone(),
+        arrive_at: None,
+        reset: true,
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn restore_snapshot(
+    mut replay: ResMut<Replay>,
+    mut scenes_ready: EventReader<SceneReady>,
+    mut progress: ResMut<CampaignProgress>,
+    mut player: ResMut<Player>,
+    mut statue_holders: ResMut<StatueHolders>,
+    mut puzzle_state: ResMut<PuzzleState>,
+    mut goals: ResMut<GoalProgress>,
+) {
+    for ready in scenes_ready.iter() {
+        // later scenes are doors or console resets being replayed
+        if replay.next.is_some() || ready.kind != SceneKind::Room(replay.recording.room.clone()) {
+            continue;
+        }
+        let recording = &replay.recording;
+        progress.completed = recording.completed_rooms.iter().cloned().collect();
+        player.inventory = recording.inventory.iter().cloned().collect();
+        player.equipped = recording.equipped;
+        statue_holders.held_statues = recording.held_statues.iter().copied().collect();
+        puzzle_state.0 = recording.solved;
+        goals.completed = recording.completed_goals.iter().cloned().collect();
+        goals.revealed_posters = recording.revealed_posters.iter().cloned().collect();
+        replay.next = Some(0);
+    }
+}
+
+/// Replaces this frame's real keyboard and mouse input with the recorded
+/// input, and moves the game clock on by the recorded delta.
+fn replay_input(
+    mut replay: ResMut<Replay>,
+    mut game_time: ResMut<GameTime>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut mouse_motion: ResMut<Events<MouseMotion>>,
+    mut console: EventWriter<ConsoleCommand>,
+) {
+    let index = match replay.next {
+        Some(index) if index < replay.recording.frames.len() => index,
+        _ => {
+            // nothing moves while the room loads
+            game_time.advance(0.0);
+            return;
+        }
+    };
+    replay.next = Some(index + 1);
+    replay.playing = Some(index);
+    let frame = replay.recording.frames[index].clone();
+    game_time.advance(frame.delta);
+
+    replay.keys.update();
+    for key in frame.pressed.iter() {
+        replay.keys.press(*key);
+    }
+    for key in frame.released.iter() {
+        replay.keys.release(*key);
+    }
+    *keys = replay.keys.clone();
+    mouse_motion.drain().for_each(drop);
+    for delta in frame.mouse_motion.iter() {
+        mouse_motion.send(MouseMotion { delta: *delta });
+    }
+    for command in frame.console.iter() {
+        console.send(command.clone());
+    }
+}
+
+fn follow_recorded_camera(
+    mut replay: ResMut<Replay>,
+    mut camera: Query<&mut Transform, With<FlyCam>>,
+) {
+    let (translation, rotation) = match replay.current() {
+        Some(frame) => frame.camera,
+        None => return,
+    };
+    for mut transform in camera.iter_mut() {
+        replay.drift = replay
+            .drift
+            .max(transform.translation.distance(translation));
+        transform.translation = translation;
+        transform.rotation = rotation;
+    }
+}
+
+fn finish_replay(
+    mut replay: ResMut<Replay>,
+    puzzle_state: Res<PuzzleState>,
+    player: Res<Player>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let frames = replay.recording.frames.len();
+    if replay.next != Some(frames) {
+        return;
+    }
+
+    let mut inventory: Vec<_> = player.inventory.iter().cloned().collect();
+    inventory.sort();
+    println!("replayed {} frames in {}", frames, replay.recording.room);
+    println!("puzzle solved: {}", puzzle_state.0);
+    println!("inventory: {}", inventory.join(", "));
+    println!(
+        "camera drifted up to {:.3} from the recorded path",
+        replay.drift
+    );
+    replay.next = None;
+    replay.playing = None;
+    exit.send(AppExit);
+}
+
+/// Plays `recording` back instead of recording, see the module docs.
+pub struct ReplayPlugin {
+    pub recording: Recording,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(Recorder {
+            enabled: false,
+            ..Default::default()
+        });
+        app.insert_resource(Replay::new(self.recording.clone()));
+        app.insert_resource(GameTime::manual());
+        app.add_system(start_replay.system());
+        app.add_system(restore_snapshot.system());
+        app.add_system_to_stage(
+            CoreStage::PreUpdate,
+            replay_input
+                .system()
+                .after(InputSystem)
+                .before("swallow_typed_keys"),
+        );
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            follow_recorded_camera
+                .system()
+                .label("follow_recorded_camera")
+                .before(RaycastSystem::BuildRays),
+        );
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            finish_replay.system().after("follow_recorded_camera"),
+        );
+    }
+}
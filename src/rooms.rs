@@ -0,0 +1,323 @@
+//! Rooms are glTF scenes linked through doors. Only the room the player is in
+//! is spawned; walking through an open door despawns it and streams in the
+//! linked room. The puzzle resources (`StatueHolders`, `PuzzleState`) always
+//! describe the current room and are swapped with the saved progress of each
+//! room on the way through. The room list comes from the campaign manifest.
+//! Items picked up in a room are remembered by node name and removed again
+//! when the room respawns.
+
+use crate::{
+    campaign::CampaignProgress,
+    interaction::ItemPickedUp,
+    puzzle_graph::{Goal, GoalProgress, PuzzleGraph},
+    scene_ready::{PendingScenes, SceneKind, SceneReady},
+    InsideDoor, InsideDoorAngle, Player, PuzzleDefinition, PuzzleState, StatueHolders,
+};
+use bevy::{prelude::*, scene::InstanceId};
+use bevy_flycam::FlyCam;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+/// how close, ignoring height, the player has to get to a door node to pass
+const DOOR_TRIGGER_RADIUS: f32 = 1.5;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DoorLink {
+    /// node name of the door in this room's scene
+    pub door: String,
+    /// id of the room on the other side
+    pub to: String,
+    /// where the player appears in the other room, in that room's coordinates
+    pub arrive_at: Vec3,
+    /// whether the room's puzzle has to be solved, and its door left open,
+    /// before the door can be used
+    #[serde(default)]
+    pub requires_solved: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoomDefinition {
+    pub id: String,
+    pub title: String,
+    /// asset path of the glTF scene, e.g. `er.gltf#Scene0`
+    pub scene: String,
+    /// world position of the scene origin, keeps rooms from overlapping
+    #[serde(default)]
+    pub offset: Vec3,
+    /// where the player starts when the room is entered from the menu
+    #[serde(default)]
+    pub spawn: Vec3,
+    #[serde(default)]
+    pub puzzle: PuzzleDefinition,
+    /// the room's puzzle graph, see the puzzle_graph module
+    #[serde(default)]
+    pub goals: Vec<Goal>,
+    /// rooms that have to be completed before this one can be entered
+    #[serde(default)]
+    pub unlock: Vec<String>,
+    #[serde(default)]
+    pub doors: Vec<DoorLink>,
+    /// Rhai script asset for a scene node, keyed by node name
+    #[serde(default)]
+    pub scripts: HashMap<String, String>,
+}
+
+#[derive(Default)]
+struct RoomProgress {
+    statue_holders: StatueHolders,
+    solved: bool,
+    goals: GoalProgress,
+    picked_up: HashSet<String>,
+}
+
+pub struct Rooms {
+    pub definitions: Vec<RoomDefinition>,
+    pub start: String,
+    progress: HashMap<String, RoomProgress>,
+}
+
+impl Rooms {
+    pub fn new(definitions: Vec<RoomDefinition>, start: &str) -> Self {
+        Self {
+            definitions,
+            start: start.to_string(),
+            progress: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&RoomDefinition> {
+        self.definitions.iter().find(|room| room.id == id)
+    }
+
+    pub fn is_unlocked(&self, id: &str, progress: &CampaignProgress) -> bool {
+        self.get(id)
+            .map(|room| {
+                room.unlock
+                    .iter()
+                    .all(|required| progress.is_completed(required))
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl Default for Rooms {
+    /// Empty until the campaign manifest has loaded.
+    fn default() -> Self {
+        Self::new(Vec::new(), "")
+    }
+}
+
+#[derive(Default)]
+pub struct CurrentRoom {
+    pub id: Option<String>,
+    root: Option<Entity>,
+    instance: Option<InstanceId>,
+    ready: bool,
+    /// false until the player has stepped away from the door they came in by
+    armed: bool,
+    /// names of the nodes picked up in this room
+    picked_up: HashSet<String>,
+}
+
+impl CurrentRoom {
+    pub fn instance(&self) -> Option<InstanceId> {
+        self.instance
+    }
+
+    /// true once the room's scene has spawned
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+}
+
+/// Marks the parent entity a room scene is spawned under.
+pub struct RoomRoot(pub String);
+
+pub struct EnterRoom {
+    pub room: String,
+    /// position in the room's coordinates, `None` uses the room's spawn point
+    pub arrive_at: Option<Vec3>,
+    /// throw away any saved progress, for replaying a room
+    pub reset: bool,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn enter_room(
+    mut commands: Commands,
+    mut events: EventReader<EnterRoom>,
+    asset_server: Res<AssetServer>,
+    mut scene_spawner: ResMut<SceneSpawner>,
+    mut rooms: ResMut<Rooms>,
+    mut current: ResMut<CurrentRoom>,
+    mut statue_holders: ResMut<StatueHolders>,
+    mut puzzle_state: ResMut<PuzzleState>,
+    mut puzzle: ResMut<PuzzleDefinition>,
+    mut graph: ResMut<PuzzleGraph>,
+    mut goals: ResMut<GoalProgress>,
+    mut door_angle: ResMut<InsideDoorAngle>,
+    mut player: ResMut<Player>,
+    mut pending_scenes: ResMut<PendingScenes>,
+    mut camera: Query<&mut Transform, With<FlyCam>>,
+) {
+    for event in events.iter() {
+        let definition = match rooms.get(&event.room) {
+            Some(definition) => definition.clone(),
+            None => {
+                error!("no room named {}", event.room);
+                continue;
+            }
+        };
+
+        if let Some(id) = current.id.take() {
+            let progress = RoomProgress {
+                statue_holders: std::mem::take(&mut *statue_holders),
+                solved: puzzle_state.0,
+                goals: std::mem::take(&mut *goals),
+                picked_up: std::mem::take(&mut current.picked_up),
+            };
+            rooms.progress.insert(id, progress);
+        }
+        if let Some(root) = current.root.take() {
+            commands.entity(root).despawn_recursive();
+        }
+
+        let progress = rooms.progress.remove(&definition.id);
+        let progress = if event.reset {
+            *player = Player::default();
+            RoomProgress::default()
+        } else {
+            progress.unwrap_or_default()
+        };
+        *statue_holders = progress.statue_holders;
+        puzzle_state.0 = progress.solved;
+        *goals = progress.goals;
+        *puzzle = definition.puzzle.clone();
+        graph.goals = definition.goals.clone();
+        door_angle.0 = 0.0;
+
+        let root = commands
+            .spawn()
+            .insert(Transform::from_translation(definition.offset))
+            .insert(GlobalTransform::default())
+            .insert(RoomRoot(definition.id.clone()))
+            .id();
+        let instance =
+            scene_spawner.spawn_as_child(asset_server.load(definition.scene.as_str()), root);
+        pending_scenes.watch(instance, SceneKind::Room(definition.id.clone()));
+
+        let arrive_at = event.arrive_at.unwrap_or(definition.spawn);
+        for mut transform in camera.iter_mut() {
+            transform.translation = definition.offset + arrive_at;
+        }
+
+        *current = CurrentRoom {
+            id: Some(definition.id),
+            root: Some(root),
+            instance: Some(instance),
+            ready: false,
+            armed: false,
+            picked_up: progress.picked_up,
+        };
+    }
+}
+
+fn record_pickups(mut picked_up: EventReader<ItemPickedUp>, mut current: ResMut<CurrentRoom>) {
+    for pickup in picked_up.iter() {
+        current.picked_up.insert(pickup.node.clone());
+    }
+}
+
+/// Also takes out the items picked up on an earlier visit, after `tag_stuff`
+/// has tagged them so its commands don't land on despawned entities.
+fn mark_room_ready(
+    mut commands: Commands,
+    mut scenes_ready: EventReader<SceneReady>,
+    scene_spawner: Res<SceneSpawner>,
+    names: Query<&Name>,
+    mut current: ResMut<CurrentRoom>,
+) {
+    for ready in scenes_ready.iter() {
+        if Some(ready.instance) != current.instance {
+            continue;
+        }
+        current.ready = true;
+        if let Some(instance_entities) = scene_spawner.iter_instance_entities(ready.instance) {
+            for entity in instance_entities {
+                let picked_up = names
+                    .get(entity)
+                    .map(|name| current.picked_up.contains(name.as_str()))
+                    .unwrap_or(false);
+                if picked_up {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn pass_through_doors(
+    rooms: Res<Rooms>,
+    progress: Res<CampaignProgress>,
+    puzzle_state: Res<PuzzleState>,
+    mut current: ResMut<CurrentRoom>,
+    camera: Query<&Transform, With<FlyCam>>,
+    nodes: Query<(&Name, &GlobalTransform)>,
+    puzzle_doors: Query<&InsideDoor>,
+    mut events: EventWriter<EnterRoom>,
+) {
+    let definition = match current.id.as_ref().and_then(|id| rooms.get(id)) {
+        Some(definition) => definition,
+        None => return,
+    };
+    let player = match camera.single() {
+        Ok(transform) => transform.translation,
+        Err(_) => return,
+    };
+
+    let mut near_door = false;
+    for link in definition.doors.iter() {
+        let door = nodes
+            .iter()
+            .find(|(name, _)| name.as_str() == link.door)
+            .map(|(_, transform)| transform.translation);
+        let door = match door {
+            Some(door) => door,
+            None => continue,
+        };
+        let offset = player - door;
+        if Vec3::new(offset.x, 0.0, offset.z).length() > DOOR_TRIGGER_RADIUS {
+            continue;
+        }
+        near_door = true;
+        // scripts can shut the puzzle door again after it opened
+        let open =
+            !link.requires_solved || (puzzle_state.0 && puzzle_doors.iter().all(|door| door.0));
+        if current.armed && open && rooms.is_unlocked(&link.to, &progress) {
+            events.send(EnterRoom {
+                room: link.to.clone(),
+                arrive_at: Some(link.arrive_at),
+                reset: false,
+            });
+            break;
+        }
+    }
+
+    if !near_door && !current.armed && current.ready {
+        current.armed = true;
+    }
+}
+
+pub struct RoomsPlugin;
+
+impl Plugin for RoomsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<EnterRoom>();
+        app.init_resource::<Rooms>();
+        app.init_resource::<CurrentRoom>();
+        app.add_system(enter_room.system().label("enter_room"));
+        app.add_system(record_pickups.system());
+        app.add_system(mark_room_ready.system().after("tag_stuff"));
+        app.add_system(pass_through_doors.system().before("enter_room"));
+    }
+}
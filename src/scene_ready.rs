@@ -0,0 +1,56 @@
+//! Scene readiness. Spawned glTF scenes are watched until `SceneSpawner` has
+//! created all of their entities, then a `SceneReady` event goes out exactly
+//! once for each instance so it can be tagged.
+
+use bevy::{prelude::*, scene::InstanceId};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SceneKind {
+    /// the first person inventory models under the camera
+    Player,
+    Room(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct SceneReady {
+    pub instance: InstanceId,
+    pub kind: SceneKind,
+}
+
+/// Instances spawned but not ready yet.
+#[derive(Default)]
+pub struct PendingScenes(Vec<(InstanceId, SceneKind)>);
+
+impl PendingScenes {
+    pub fn watch(&mut self, instance: InstanceId, kind: SceneKind) {
+        self.0.push((instance, kind));
+    }
+}
+
+fn detect_ready_scenes(
+    scene_spawner: Res<SceneSpawner>,
+    mut pending: ResMut<PendingScenes>,
+    mut ready: EventWriter<SceneReady>,
+) {
+    pending.0.retain(|(instance, kind)| {
+        if scene_spawner.instance_is_ready(*instance) {
+            ready.send(SceneReady {
+                instance: *instance,
+                kind: kind.clone(),
+            });
+            false
+        } else {
+            true
+        }
+    });
+}
+
+pub struct SceneReadyPlugin;
+
+impl Plugin for SceneReadyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<SceneReady>();
+        app.init_resource::<PendingScenes>();
+        app.add_system_to_stage(CoreStage::PreUpdate, detect_ready_scenes.system());
+    }
+}
@@ -0,0 +1,389 @@
+//! Rhai scripts for puzzle logic. A room lists a script per scene node in the
+//! campaign manifest, and the script may define any of these hooks:
+//!
+//! ```text
+//! fn on_interact(world, node) {}       // F1 on the node
+//! fn on_place(world, holder, item) {}  // a statue placed on the holder
+//! fn on_illuminate(world, poster) {}   // the blacklight lands on the poster
+//! fn on_solved(world) {}               // called on every script in the room
+//! ```
+//!
+//! `world` only exposes `has_item`, `give`, `take`, `show`, `hide`,
+//! `open_door` and `close_door`, which swing the puzzle door, `play_sound`
+//! and `say`, which subtitles a spoken line or clue. Calls are queued and
+//! applied once the hook returns, so scripts never touch the ECS directly.
+//! Scripted nodes that the game doesn't already know about become pickable.
+
+use crate::{
+    interaction::{InteractionEvent, ItemPlaced},
+    rooms::{CurrentRoom, Rooms},
+    subtitles::Subtitle,
+    BlacklightTarget, InsideDoor, PlaySound, Player, SoundEffect,
+};
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use rhai::{Dynamic, Engine, FuncArgs, Scope, AST};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+/// keeps a runaway script from hanging the game
+const MAX_SCRIPT_OPERATIONS: u64 = 100_000;
+
+#[derive(Debug, TypeUuid)]
+#[uuid = "6f0c1a55-93c4-4b8e-a0a4-8d27e1f2c7b3"]
+pub struct ScriptSource(pub String);
+
+#[derive(Default)]
+pub struct ScriptLoader;
+
+impl AssetLoader for ScriptLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let source = String::from_utf8(bytes.to_vec())?;
+            load_context.set_default_asset(LoadedAsset::new(ScriptSource(source)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["rhai"]
+    }
+}
+
+/// Something that happened in the world that scripts can react to.
+#[derive(Debug, Clone)]
+pub enum ScriptHook {
+    Interact { node: String },
+    Place { holder: String, item: String },
+    Illuminate { poster: String },
+    Solved,
+}
+
+#[derive(Debug, Clone)]
+enum ScriptCommand {
+    Give(String),
+    Take(String),
+    SetVisible(String, bool),
+    SetDoorOpen(bool),
+    PlaySound(SoundEffect),
+    Say(String),
+}
+
+#[derive(Default)]
+struct ScriptState {
+    inventory: HashSet<String>,
+    commands: Vec<ScriptCommand>,
+}
+
+/// The `world` value handed to hooks.
+#[derive(Clone)]
+struct ScriptWorld(Arc<Mutex<ScriptState>>);
+
+impl ScriptWorld {
+    fn push(&mut self, command: ScriptCommand) {
+        self.0.lock().unwrap().commands.push(command);
+    }
+
+    fn has_item(&mut self, name: &str) -> bool {
+        self.0.lock().unwrap().inventory.contains(name)
+    }
+
+    fn give(&mut self, name: &str) {
+        self.0.lock().unwrap().inventory.insert(name.to_string());
+        self.push(ScriptCommand::Give(name.to_string()));
+    }
+
+    fn take(&mut self, name: &str) {
+        self.0.lock().unwrap().inventory.remove(name);
+        self.push(ScriptCommand::Take(name.to_string()));
+    }
+
+    fn play_sound(&mut self, name: &str) {
+        let effect = match name {
+            "pickup" => SoundEffect::Pickup,
+            "place" => SoundEffect::Place,
+            "wrong" => SoundEffect::WrongPlacement,
+            "door_creak" => SoundEffect::DoorCreak,
+            _ => {
+                warn!("scripts have no sound named {}", name);
+                return;
+            }
+        };
+        self.push(ScriptCommand::PlaySound(effect));
+    }
+}
+
+pub struct ScriptEngine(Engine);
+
+impl Default for ScriptEngine {
+    fn default() -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_SCRIPT_OPERATIONS);
+        engine.on_print(|text| info!("script: {}", text));
+        engine
+            .register_type_with_name::<ScriptWorld>("World")
+            .register_fn("has_item", ScriptWorld::has_item)
+            .register_fn("give", ScriptWorld::give)
+            .register_fn("take", ScriptWorld::take)
+            .register_fn("show", |world: &mut ScriptWorld, node: &str| {
+                world.push(ScriptCommand::SetVisible(node.to_string(), true))
+            })
+            .register_fn("hide", |world: &mut ScriptWorld, node: &str| {
+                world.push(ScriptCommand::SetVisible(node.to_string(), false))
+            })
+            .register_fn("open_door", |world: &mut ScriptWorld| {
+                world.push(ScriptCommand::SetDoorOpen(true))
+            })
+            .register_fn("close_door", |world: &mut ScriptWorld| {
+                world.push(ScriptCommand::SetDoorOpen(false))
+            })
+            .register_fn("play_sound", ScriptWorld::play_sound)
+            .register_fn("say", |world: &mut ScriptWorld, text: &str| {
+                world.push(ScriptCommand::Say(text.to_string()))
+            });
+        Self(engine)
+    }
+}
+
+/// Scripts of the current room, keyed by node name.
+#[derive(Default)]
+pub struct RoomScripts {
+    room: Option<String>,
+    handles: HashMap<String, Handle<ScriptSource>>,
+    compiled: HashMap<String, AST>,
+    /// nodes whose script has to be (re)compiled once its source is loaded
+    pending: HashSet<String>,
+}
+
+impl RoomScripts {
+    pub fn contains(&self, node: &str) -> bool {
+        self.handles.contains_key(node)
+    }
+}
+
+fn load_room_scripts(
+    asset_server: Res<AssetServer>,
+    rooms: Res<Rooms>,
+    current: Res<CurrentRoom>,
+    mut scripts: ResMut<RoomScripts>,
+) {
+    if scripts.room == current.id {
+        return;
+    }
+    let handles: HashMap<_, _> = current
+        .id
+        .as_ref()
+        .and_then(|id| rooms.get(id))
+        .map(|room| {
+            room.scripts
+                .iter()
+                .map(|(node, path)| (node.clone(), asset_server.load(path.as_str())))
+                .collect()
+        })
+        .unwrap_or_default();
+    *scripts = RoomScripts {
+        room: current.id.clone(),
+        pending: handles.keys().cloned().collect(),
+        handles,
+        compiled: HashMap::new(),
+    };
+}
+
+/// Compiles scripts once they have loaded, and again whenever they change on
+/// disk.
+fn compile_scripts(
+    mut asset_events: EventReader<AssetEvent<ScriptSource>>,
+    sources: Res<Assets<ScriptSource>>,
+    engine: Res<ScriptEngine>,
+    mut scripts: ResMut<RoomScripts>,
+) {
+    let scripts = &mut *scripts;
+    for event in asset_events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            for (node, script) in scripts.handles.iter() {
+                if script == handle {
+                    scripts.pending.insert(node.clone());
+                }
+            }
+        }
+    }
+
+    let handles = &scripts.handles;
+    let ready: Vec<_> = scripts
+        .pending
+        .iter()
+        .filter_map(|node| {
+            let source = handles.get(node).and_then(|handle| sources.get(handle))?;
+            Some((node.clone(), source))
+        })
+        .collect();
+    for (node, source) in ready {
+        scripts.pending.remove(&node);
+        match engine.0.compile(source.0.as_str()) {
+            Ok(ast) => {
+                scripts.compiled.insert(node, ast);
+            }
+            Err(err) => error!("script for {} doesn't compile: {}", node, err),
+        }
+    }
+}
+
+fn hooks_from_interactions(
+    mut interactions: EventReader<InteractionEvent>,
+    mut placed: EventReader<ItemPlaced>,
+    mut hooks: EventWriter<ScriptHook>,
+) {
+    for interaction in interactions.iter() {
+        if let InteractionEvent::Use { name, .. } = interaction {
+            hooks.send(ScriptHook::Interact { node: name.clone() });
+        }
+    }
+    for event in placed.iter() {
+        hooks.send(ScriptHook::Place {
+            holder: event.holder.clone(),
+            item: event.item.clone(),
+        });
+    }
+}
+
+fn detect_illumination(
+    player: Res<Player>,
+    target: Res<BlacklightTarget>,
+    mut last_poster: Local<Option<String>>,
+    mut hooks: EventWriter<ScriptHook>,
+) {
+    let poster = if player.equipped_name() == "InvBlacklightFlashlight" {
+        target.0.as_ref().map(|target| target.name.clone())
+    } else {
+        None
+    };
+    if poster != *last_poster {
+        if let Some(poster) = poster.clone() {
+            hooks.send(ScriptHook::Illuminate { poster });
+        }
+        *last_poster = poster;
+    }
+}
+
+fn call_hook(
+    engine: &Engine,
+    node: &str,
+    ast: &AST,
+    name: &str,
+    args: impl FuncArgs,
+    arity: usize,
+) {
+    let defined = ast
+        .iter_functions()
+        .any(|function| function.name == name && function.params.len() == arity);
+    if !defined {
+        return;
+    }
+    let mut scope = Scope::new();
+    if let Err(err) = engine.call_fn::<Dynamic>(&mut scope, ast, name, args) {
+        error!("{} in the script for {} failed: {}", name, node, err);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_script_hooks(
+    mut hooks: EventReader<ScriptHook>,
+    engine: Res<ScriptEngine>,
+    scripts: Res<RoomScripts>,
+    mut player: ResMut<Player>,
+    mut sounds: EventWriter<PlaySound>,
+    mut subtitles: EventWriter<Subtitle>,
+    nodes: Query<(&Name, Option<&Children>)>,
+    mut visibles: Query<&mut Visible>,
+    mut doors: Query<&mut InsideDoor>,
+) {
+    let state = Arc::new(Mutex::new(ScriptState::default()));
+    let world = ScriptWorld(state.clone());
+    for hook in hooks.iter() {
+        state.lock().unwrap().inventory = player.inventory.clone();
+        match hook {
+            ScriptHook::Interact { node } => {
+                if let Some(ast) = scripts.compiled.get(node) {
+                    let args = (world.clone(), node.clone());
+                    call_hook(&engine.0, node, ast, "on_interact", args, 2);
+                }
+            }
+            ScriptHook::Place { holder, item } => {
+                if let Some(ast) = scripts.compiled.get(holder) {
+                    let args = (world.clone(), holder.clone(), item.clone());
+                    call_hook(&engine.0, holder, ast, "on_place", args, 3);
+                }
+            }
+            ScriptHook::Illuminate { poster } => {
+                if let Some(ast) = scripts.compiled.get(poster) {
+                    let args = (world.clone(), poster.clone());
+                    call_hook(&engine.0, poster, ast, "on_illuminate", args, 2);
+                }
+            }
+            ScriptHook::Solved => {
+                for (node, ast) in scripts.compiled.iter() {
+                    call_hook(&engine.0, node, ast, "on_solved", (world.clone(),), 1);
+                }
+            }
+        }
+
+        let commands = std::mem::take(&mut state.lock().unwrap().commands);
+        for command in commands {
+            match command {
+                ScriptCommand::Give(name) => {
+                    player.inventory.insert(name);
+                }
+                ScriptCommand::Take(name) => {
+                    player.inventory.remove(&name);
+                }
+                ScriptCommand::SetVisible(node, visible) => {
+                    for (name, children) in nodes.iter() {
+                        if name.as_str() != node {
+                            continue;
+                        }
+                        for child in children.iter().flat_map(|children| children.iter()) {
+                            if let Ok(mut v) = visibles.get_mut(*child) {
+                                v.is_visible = visible;
+                            }
+                        }
+                    }
+                }
+                ScriptCommand::SetDoorOpen(open) => {
+                    for mut door in doors.iter_mut() {
+                        door.0 = open;
+                    }
+                }
+                ScriptCommand::PlaySound(effect) => sounds.send(PlaySound::at_listener(effect)),
+                ScriptCommand::Say(text) => subtitles.send(Subtitle::new(&text)),
+            }
+        }
+    }
+}
+
+/// Runs the current room's Rhai scripts in response to `ScriptHook` events.
+pub struct ScriptingPlugin;
+
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<ScriptHook>();
+        app.add_asset::<ScriptSource>();
+        app.init_asset_loader::<ScriptLoader>();
+        app.init_resource::<ScriptEngine>();
+        app.init_resource::<RoomScripts>();
+        app.add_system(load_room_scripts.system().label("load_room_scripts"));
+        app.add_system(compile_scripts.system().after("load_room_scripts"));
+        app.add_system(hooks_from_interactions.system());
+        app.add_system(detect_illumination.system());
+        app.add_system(run_script_hooks.system());
+    }
+}
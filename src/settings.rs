@@ -0,0 +1,260 @@
+use crate::{
+    has_window,
+    localization::{Localization, FALLBACK_LANGUAGE, LANGUAGES},
+    storage::{self, StorageConfig},
+};
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    render::camera::{Camera, CameraProjection, PerspectiveProjection},
+};
+use bevy_egui::{egui, EguiContext, EguiSettings};
+use bevy_flycam::FlyCam;
+use serde::{Deserialize, Serialize};
+
+const SETTINGS_KEY: &str = "bevy_er_settings";
+const SAVE_DELAY_SECONDS: f64 = 1.0;
+
+/// Player tunable options, persisted between sessions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// vertical field of view in degrees
+    pub fov: f32,
+    pub sensitivity: f32,
+    pub speed: f32,
+    pub volume: f32,
+    pub effects_volume: f32,
+    pub ambience_volume: f32,
+    pub muted: bool,
+    pub vsync: bool,
+    /// only read at startup, bevy builds its pipelines with a fixed sample count
+    pub msaa_samples: u32,
+    /// one of `LANGUAGES`
+    pub language: String,
+    /// mark the statue colors with shapes, see the accessibility module
+    pub color_symbols: bool,
+    /// name the color of the statue or poster under the crosshair
+    pub color_names: bool,
+    pub high_contrast: bool,
+    pub subtitles: bool,
+    /// egui scale factor for the inventory, menus and panels
+    pub ui_scale: f32,
+    /// cut between cutscene shots and snap the door open, behind fades,
+    /// instead of moving them; the flycam has no head bob to turn off
+    pub reduced_motion: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            fov: 45.0,
+            sensitivity: 0.00012,
+            speed: 12.0,
+            volume: 1.0,
+            effects_volume: 1.0,
+            ambience_volume: 0.5,
+            muted: false,
+            vsync: true,
+            msaa_samples: 1,
+            language: FALLBACK_LANGUAGE.to_string(),
+            color_symbols: false,
+            color_names: false,
+            high_contrast: false,
+            subtitles: false,
+            ui_scale: 1.0,
+            reduced_motion: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        storage::load(SETTINGS_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self, storage_config: &StorageConfig) {
+        storage::save(storage_config, SETTINGS_KEY, self);
+    }
+}
+
+#[derive(Default)]
+pub struct SettingsUi {
+    pub open: bool,
+    /// the UI scale while its slider is dragged, applied on release
+    ui_scale_drag: Option<f32>,
+}
+
+fn toggle_settings(keyboard_input: Res<Input<KeyCode>>, mut settings_ui: ResMut<SettingsUi>) {
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        settings_ui.open = !settings_ui.open;
+    }
+}
+
+fn settings_ui(
+    egui_context: Res<EguiContext>,
+    mut settings_ui: ResMut<SettingsUi>,
+    mut settings: ResMut<Settings>,
+    localization: Res<Localization>,
+) {
+    if !settings_ui.open {
+        return;
+    }
+
+    let mut open = true;
+    let mut edited = settings.clone();
+    let tr = |key: &str| localization.tr(key);
+    egui::Window::new(tr("settings-title"))
+        .id(egui::Id::new("settings"))
+        .open(&mut open)
+        .default_width(250.0)
+        .show(egui_context.ctx(), |ui| {
+            ui.heading(tr("settings-controls"));
+            ui.add(egui::Slider::new(&mut edited.fov, 30.0..=110.0).text(tr("settings-fov")));
+            ui.add(
+                egui::Slider::new(&mut edited.sensitivity, 0.00002..=0.0005)
+                    .text(tr("settings-sensitivity")),
+            );
+            ui.add(egui::Slider::new(&mut edited.speed, 2.0..=30.0).text(tr("settings-speed")));
+            ui.separator();
+            ui.heading(tr("settings-audio"));
+            ui.add(egui::Slider::new(&mut edited.volume, 0.0..=1.0).text(tr("settings-volume")));
+            ui.add(
+                egui::Slider::new(&mut edited.effects_volume, 0.0..=1.0)
+                    .text(tr("settings-effects")),
+            );
+            ui.add(
+                egui::Slider::new(&mut edited.ambience_volume, 0.0..=1.0)
+                    .text(tr("settings-ambience")),
+            );
+            ui.checkbox(&mut edited.muted, tr("settings-mute"));
+            ui.separator();
+            ui.heading(tr("settings-graphics"));
+            ui.checkbox(&mut edited.vsync, tr("settings-vsync"));
+            ui.horizontal(|ui| {
+                ui.label(tr("settings-antialiasing"));
+                ui.radio_value(&mut edited.msaa_samples, 1, tr("settings-antialiasing-off"));
+                ui.radio_value(&mut edited.msaa_samples, 4, "4x");
+            });
+            ui.label(tr("settings-antialiasing-restart"));
+            ui.separator();
+            ui.heading(tr("settings-accessibility"));
+            ui.checkbox(&mut edited.color_symbols, tr("settings-color-symbols"));
+            ui.checkbox(&mut edited.color_names, tr("settings-color-names"));
+            ui.checkbox(&mut edited.high_contrast, tr("settings-high-contrast"));
+            ui.checkbox(&mut edited.subtitles, tr("settings-subtitles"));
+            ui.checkbox(&mut edited.reduced_motion, tr("settings-reduced-motion"));
+            // rescaling mid drag would move the slider out from under the
+            // pointer
+            let mut ui_scale = settings_ui.ui_scale_drag.unwrap_or(edited.ui_scale);
+            let slider =
+                ui.add(egui::Slider::new(&mut ui_scale, 0.75..=2.0).text(tr("settings-ui-scale")));
+            if slider.dragged() {
+                settings_ui.ui_scale_drag = Some(ui_scale);
+            } else {
+                settings_ui.ui_scale_drag = None;
+                edited.ui_scale = ui_scale;
+            }
+            ui.separator();
+            let language_name = LANGUAGES
+                .iter()
+                .find(|(id, _)| *id == edited.language)
+                .map(|(_, name)| *name)
+                .unwrap_or("");
+            egui::ComboBox::from_label(tr("settings-language"))
+                .selected_text(language_name)
+                .show_ui(ui, |ui| {
+                    for (id, name) in LANGUAGES.iter() {
+                        ui.selectable_value(&mut edited.language, id.to_string(), *name);
+                    }
+                });
+            ui.separator();
+            if ui.button(tr("settings-reset")).clicked() {
+                edited = Settings::default();
+            }
+        });
+
+    if edited != *settings {
+        *settings = edited;
+    }
+    if !open {
+        settings_ui.open = false;
+    }
+}
+
+/// Saves a second after the last change, so dragging a slider doesn't write
+/// every frame, and straight away on quitting. A closed browser tab sends no
+/// `AppExit`, so the delay is kept short.
+fn save_settings(
+    storage_config: Res<StorageConfig>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut exit: EventReader<AppExit>,
+    mut changed_at: Local<Option<f64>>,
+) {
+    let now = time.seconds_since_startup();
+    if settings.is_changed() && !settings.is_added() {
+        *changed_at = Some(now);
+    }
+    let exiting = exit.iter().next().is_some();
+    if let Some(at) = *changed_at {
+        if exiting || now - at >= SAVE_DELAY_SECONDS {
+            settings.save(&storage_config);
+            *changed_at = None;
+        }
+    }
+}
+
+/// Sensitivity and speed reach the flycam through `freeze_flycam` in the
+/// input module, which zeroes them while input is blocked.
+fn apply_settings(
+    settings: Res<Settings>,
+    windows: Res<Windows>,
+    mut cameras: Query<(&mut Camera, &mut PerspectiveProjection), With<FlyCam>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    // bevy only rebuilds the projection matrix on resize, so do it here too
+    for (mut camera, mut projection) in cameras.iter_mut() {
+        projection.fov = settings.fov.to_radians();
+        if let Some(window) = windows.get(camera.window) {
+            projection.update(window.width(), window.height());
+            camera.projection_matrix = projection.get_projection_matrix();
+        }
+    }
+}
+
+fn apply_window_settings(settings: Res<Settings>, mut windows: ResMut<Windows>) {
+    if settings.is_changed() {
+        if let Some(window) = windows.get_primary_mut() {
+            window.set_vsync(settings.vsync);
+        }
+    }
+}
+
+fn apply_ui_scale(settings: Res<Settings>, mut egui_settings: ResMut<EguiSettings>) {
+    if settings.is_changed() {
+        egui_settings.scale_factor = settings.ui_scale as f64;
+    }
+}
+
+/// Loads the persisted settings and applies them live to the camera, the
+/// window and egui. `msaa_samples` is left to the host app, which has to
+/// insert `Msaa` before `DefaultPlugins` for it to take effect.
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(Settings::load());
+        app.init_resource::<StorageConfig>();
+        app.init_resource::<SettingsUi>();
+        app.add_system(toggle_settings.system());
+        app.add_system(settings_ui.system().with_run_criteria(has_window.system()));
+        app.add_system_to_stage(CoreStage::Last, save_settings.system());
+        app.add_system(apply_settings.system());
+        app.add_system(apply_window_settings.system());
+        app.add_system(apply_ui_scale.system());
+    }
+}
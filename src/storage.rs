@@ -0,0 +1,75 @@
+//! Small key/value persistence layer. Native builds keep one `<key>.ron` file
+//! per key in the working directory, web builds use `localStorage`. Saving
+//! can be switched off through `StorageConfig`, e.g. so replays leave the
+//! player's files alone.
+
+use bevy::log::error;
+
+/// Whether `save` writes anything.
+#[derive(Debug, Clone)]
+pub struct StorageConfig {
+    pub saving: bool,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self { saving: true }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path_for_key(key: &str) -> std::path::PathBuf {
+    std::path::PathBuf::from(format!("{}.ron", key))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_string(key: &str) -> Option<String> {
+    std::fs::read_to_string(path_for_key(key)).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_string(key: &str, value: &str) {
+    if let Err(err) = std::fs::write(path_for_key(key), value) {
+        error!("unable to save {}: {}", key, err);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load_string(key: &str) -> Option<String> {
+    local_storage()?.get_item(key).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_string(key: &str, value: &str) {
+    if let Some(storage) = local_storage() {
+        if storage.set_item(key, value).is_err() {
+            error!("unable to save {}", key);
+        }
+    }
+}
+
+pub fn load<T: serde::de::DeserializeOwned>(key: &str) -> Option<T> {
+    let text = load_string(key)?;
+    match ron::de::from_str(&text) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            error!("unable to parse {}: {}", key, err);
+            None
+        }
+    }
+}
+
+pub fn save<T: serde::Serialize>(config: &StorageConfig, key: &str, value: &T) {
+    if !config.saving {
+        return;
+    }
+    match ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()) {
+        Ok(text) => save_string(key, &text),
+        Err(err) => error!("unable to serialize {}: {}", key, err),
+    }
+}
@@ -0,0 +1,119 @@
+//! Subtitles and captions. Every `PlaySound` gets a caption describing it,
+//! the blacklight hum is captioned when it starts, and scripts can show a
+//! spoken line or clue with `world.say(text)`. They show at the bottom of
+//! the screen while `Settings::subtitles` is on, even when muted.
+//!
+//! Captions are Fluent keys, a `say` text is looked up as a key first and
+//! shown as written otherwise.
+
+use crate::{
+    audio::{LoopLevels, PlaySound, SoundEffect},
+    game_time::GameTime,
+    has_window,
+    localization::Localization,
+    settings::Settings,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+const SUBTITLE_SECONDS: f32 = 3.0;
+/// older lines are dropped past this many
+const MAX_LINES: usize = 3;
+
+/// A line to show, either a Fluent key or plain text.
+#[derive(Debug, Clone)]
+pub struct Subtitle {
+    pub text: String,
+    pub seconds: f32,
+}
+
+impl Subtitle {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            seconds: SUBTITLE_SECONDS,
+        }
+    }
+}
+
+fn caption_key(effect: SoundEffect) -> &'static str {
+    match effect {
+        SoundEffect::Pickup => "caption-pickup",
+        SoundEffect::Place => "caption-place",
+        SoundEffect::WrongPlacement => "caption-wrong",
+        SoundEffect::DoorCreak => "caption-door-creak",
+    }
+}
+
+fn caption_sounds(
+    mut sounds: EventReader<PlaySound>,
+    levels: Res<LoopLevels>,
+    mut humming: Local<bool>,
+    mut subtitles: EventWriter<Subtitle>,
+) {
+    for sound in sounds.iter() {
+        subtitles.send(Subtitle::new(caption_key(sound.effect)));
+    }
+    // the ambience never stops, so it goes without a caption
+    let hum = levels.blacklight_hum > 0.0;
+    if hum && !*humming {
+        subtitles.send(Subtitle::new("caption-blacklight-hum"));
+    }
+    *humming = hum;
+}
+
+#[derive(Default)]
+struct Lines(Vec<(String, Timer)>);
+
+fn show_subtitles(
+    time: Res<GameTime>,
+    egui_context: Res<EguiContext>,
+    settings: Res<Settings>,
+    localization: Res<Localization>,
+    mut subtitles: EventReader<Subtitle>,
+    mut lines: Local<Lines>,
+) {
+    for subtitle in subtitles.iter() {
+        let text = localization.tr_or(&subtitle.text, &subtitle.text);
+        // the same sound twice in a row only keeps its line up longer
+        lines.0.retain(|(line, _)| *line != text);
+        lines
+            .0
+            .push((text, Timer::from_seconds(subtitle.seconds, false)));
+    }
+    let overflow = lines.0.len().saturating_sub(MAX_LINES);
+    lines.0.drain(..overflow);
+    for (_, timer) in lines.0.iter_mut() {
+        timer.tick(time.delta());
+    }
+    lines.0.retain(|(_, timer)| !timer.finished());
+
+    if !settings.subtitles || lines.0.is_empty() {
+        return;
+    }
+    egui::Window::new("subtitles")
+        .title_bar(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -40.0])
+        .show(egui_context.ctx(), |ui| {
+            for (line, _) in lines.0.iter() {
+                ui.label(line.as_str());
+            }
+        });
+}
+
+/// Captions for sound effects and lines from scripts.
+pub struct SubtitlesPlugin;
+
+impl Plugin for SubtitlesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<Subtitle>();
+        app.add_system(caption_sounds.system().label("caption_sounds"));
+        app.add_system(
+            show_subtitles
+                .system()
+                .with_run_criteria(has_window.system())
+                .after("caption_sounds"),
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! Touch controls for phones, switched on by the first touch. A finger
+//! landing on the left half of the screen becomes a movement stick and one
+//! on the right half a look stick; both are anchored where the finger came
+//! down. A Use button stands in for F1 and tapping an inventory slot equips
+//! it.
+//!
+//! The sticks press the same keys a keyboard would, WASD to move and QE/RF
+//! to look, so the flycam and `keyboard_input_system` need no changes. That
+//! makes them digital: past the dead zone a stick moves or turns at full
+//! speed whatever its tilt.
+//!
+//! Touch positions are handled as logical pixels from the top left, which
+//! matches egui once divided by its scale factor. bevy_winit reports them
+//! from the bottom on android and ios, so those are flipped. In a browser
+//! winit reports touches as mouse input, so `browser` forwards the canvas'
+//! touch events instead.
+
+use crate::{has_window, input::InputBlock, localization::Localization, InventorySlots, Player};
+use bevy::{
+    input::{
+        touch::{Touch, Touches},
+        InputSystem,
+    },
+    prelude::*,
+};
+use bevy_egui::{egui, EguiContext, EguiSettings};
+use std::collections::HashSet;
+
+/// how far a stick's knob can travel from where the finger came down
+const STICK_RADIUS: f32 = 60.0;
+/// fraction of `STICK_RADIUS` that is ignored
+const DEAD_ZONE: f32 = 0.3;
+const BUTTON_RADIUS: f32 = 40.0;
+
+/// keys the sticks press, as (negative, positive) along x and y
+const MOVE_KEYS: [(KeyCode, KeyCode); 2] = [(KeyCode::A, KeyCode::D), (KeyCode::W, KeyCode::S)];
+const LOOK_KEYS: [(KeyCode, KeyCode); 2] = [(KeyCode::Q, KeyCode::E), (KeyCode::R, KeyCode::F)];
+
+struct Stick {
+    touch: u64,
+    origin: Vec2,
+    position: Vec2,
+}
+
+impl Stick {
+    /// knob offset scaled to -1.0..=1.0 along each axis
+    fn tilt(&self) -> Vec2 {
+        let offset = self.position - self.origin;
+        if offset.length() > STICK_RADIUS {
+            offset.normalize()
+        } else {
+            offset / STICK_RADIUS
+        }
+    }
+
+    fn keys(&self, keys: &[(KeyCode, KeyCode); 2], pressed: &mut HashSet<KeyCode>) {
+        let tilt = self.tilt();
+        for (value, (negative, positive)) in [tilt.x, tilt.y].iter().zip(keys.iter()) {
+            if *value < -DEAD_ZONE {
+                pressed.insert(*negative);
+            } else if *value > DEAD_ZONE {
+                pressed.insert(*positive);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TouchControls {
+    /// set by the first touch and left on
+    pub enabled: bool,
+    move_stick: Option<Stick>,
+    look_stick: Option<Stick>,
+    /// keys currently held down by the sticks
+    held: HashSet<KeyCode>,
+    /// keys pressed by a tap, released the next frame
+    tapped: Vec<KeyCode>,
+}
+
+/// where the Use button sits on a screen of `size` points
+fn use_button_center(size: Vec2) -> Vec2 {
+    Vec2::new(size.x - 80.0, size.y - 200.0)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn touch_input(
+    touches: Res<Touches>,
+    windows: Res<Windows>,
+    egui_settings: Res<EguiSettings>,
+    input_block: Res<InputBlock>,
+    slots: Res<InventorySlots>,
+    mut player: ResMut<Player>,
+    mut controls: ResMut<TouchControls>,
+    mut keys: ResMut<Input<KeyCode>>,
+) {
+    let controls = &mut *controls;
+    for key in controls.tapped.drain(..) {
+        keys.release(key);
+    }
+
+    let scale = egui_settings.scale_factor as f32;
+    let window_size = windows
+        .get_primary()
+        .map(|window| Vec2::new(window.width(), window.height()))
+        .unwrap_or_default();
+    let size = window_size / scale;
+    let to_points = |touch: &Touch| {
+        let mut position = touch.position();
+        // bevy_winit measures touches from the bottom on android and ios
+        if cfg!(any(target_os = "android", target_os = "ios")) {
+            position.y = window_size.y - position.y;
+        }
+        position / scale
+    };
+    for touch in touches.iter_just_pressed() {
+        controls.enabled = true;
+        if input_block.is_blocked() {
+            continue;
+        }
+        let point = to_points(touch);
+        let slot = slots
+            .0
+            .iter()
+            .position(|slot| slot.contains(egui::pos2(point.x, point.y)));
+        if let Some(index) = slot {
+            player.equipped = index;
+            continue;
+        }
+        if point.distance(use_button_center(size)) <= BUTTON_RADIUS {
+            keys.press(KeyCode::F1);
+            controls.tapped.push(KeyCode::F1);
+            continue;
+        }
+        let stick = if point.x < size.x / 2.0 {
+            &mut controls.move_stick
+        } else {
+            &mut controls.look_stick
+        };
+        if stick.is_none() {
+            *stick = Some(Stick {
+                touch: touch.id(),
+                origin: point,
+                position: point,
+            });
+        }
+    }
+
+    for stick in [&mut controls.move_stick, &mut controls.look_stick].iter_mut() {
+        let touch = stick
+            .as_ref()
+            .and_then(|stick| touches.get_pressed(stick.touch));
+        match touch {
+            Some(touch) if !input_block.is_blocked() => {
+                if let Some(stick) = stick.as_mut() {
+                    stick.position = to_points(touch);
+                }
+            }
+            _ => **stick = None,
+        }
+    }
+
+    let mut pressed = HashSet::new();
+    if let Some(stick) = controls.move_stick.as_ref() {
+        stick.keys(&MOVE_KEYS, &mut pressed);
+    }
+    if let Some(stick) = controls.look_stick.as_ref() {
+        stick.keys(&LOOK_KEYS, &mut pressed);
+    }
+    for key in controls.held.difference(&pressed) {
+        keys.release(*key);
+    }
+    for key in pressed.difference(&controls.held) {
+        keys.press(*key);
+    }
+    controls.held = pressed;
+}
+
+fn draw_stick(painter: &egui::Painter, stick: Option<&Stick>, resting: Vec2) {
+    let (origin, knob) = match stick {
+        Some(stick) => (stick.origin, stick.origin + stick.tilt() * STICK_RADIUS),
+        None => (resting, resting),
+    };
+    let alpha = if stick.is_some() { 160 } else { 60 };
+    let color = egui::Color32::from_white_alpha(alpha);
+    painter.circle_stroke(
+        egui::pos2(origin.x, origin.y),
+        STICK_RADIUS,
+        egui::Stroke::new(3.0, color),
+    );
+    painter.circle_filled(egui::pos2(knob.x, knob.y), STICK_RADIUS * 0.4, color);
+}
+
+fn draw_touch_controls(
+    egui_context: Res<EguiContext>,
+    controls: Res<TouchControls>,
+    input_block: Res<InputBlock>,
+    localization: Res<Localization>,
+) {
+    if !controls.enabled || input_block.is_blocked() {
+        return;
+    }
+
+    let ctx = egui_context.ctx();
+    let screen = ctx.input().screen_rect();
+    let size = Vec2::new(screen.width(), screen.height());
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("touch_controls"),
+    ));
+    draw_stick(
+        &painter,
+        controls.move_stick.as_ref(),
+        Vec2::new(100.0, size.y - 100.0),
+    );
+    draw_stick(
+        &painter,
+        controls.look_stick.as_ref(),
+        Vec2::new(size.x - 200.0, size.y - 100.0),
+    );
+    let button = use_button_center(size);
+    let button = egui::pos2(button.x, button.y);
+    painter.circle(
+        button,
+        BUTTON_RADIUS,
+        egui::Color32::from_black_alpha(120),
+        egui::Stroke::new(3.0, egui::Color32::WHITE),
+    );
+    painter.text(
+        button,
+        egui::Align2::CENTER_CENTER,
+        localization.tr("touch-use"),
+        egui::TextStyle::Button,
+        egui::Color32::WHITE,
+    );
+}
+
+/// Virtual sticks, a Use button and tappable inventory slots for touch
+/// screens.
+pub struct TouchPlugin;
+
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<TouchControls>();
+        app.add_system_to_stage(
+            CoreStage::PreUpdate,
+            touch_input.system().label("touch_input").after(InputSystem),
+        );
+        app.add_system(
+            draw_touch_controls
+                .system()
+                .with_run_criteria(has_window.system()),
+        );
+    }
+}
@@ -0,0 +1,324 @@
+//! Checks that a scene has every node the game looks up by name. Used when a
+//! scene finishes spawning and by the `validate-scene` binary, which reads
+//! the glTF files directly.
+
+use crate::{
+    puzzle_graph::WorldChange, rooms::RoomDefinition, Location, PuzzleDefinition, StatueColor,
+};
+use std::{collections::HashMap, fmt};
+
+/// nodes of `pl.gltf` the inventory relies on
+pub const PLAYER_SCENE_NODES: &[&str] = &[
+    "InvBallStatueRed",
+    "InvBallStatueGreen",
+    "InvBallStatueBlue",
+    "InvBlacklightFlashlight",
+];
+
+/// nodes every room needs whatever its solution
+const ROOM_SCENE_NODES: &[&str] = &[
+    "BlacklightFlashlight",
+    "RedPoster",
+    "GreenPoster",
+    "BluePoster",
+    "RedPosterUV",
+    "GreenPosterUV",
+    "BluePosterUV",
+    "InsideDoor",
+];
+
+/// A node as far as validation cares. `has_children` is true for nodes with
+/// a mesh or child nodes, which is what tagging needs to find anything to
+/// tag.
+#[derive(Debug, Clone)]
+pub struct SceneNode {
+    pub name: String,
+    pub has_children: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    MissingNode {
+        scene: String,
+        name: String,
+        /// the closest name the scene does have
+        similar: Option<String>,
+    },
+    NoChildren {
+        scene: String,
+        name: String,
+    },
+    DuplicateName {
+        scene: String,
+        name: String,
+        count: usize,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::MissingNode {
+                scene,
+                name,
+                similar,
+            } => {
+                write!(f, "{}: no node named {}", scene, name)?;
+                if let Some(similar) = similar {
+                    write!(f, ", did it get renamed to {} in Blender?", similar)?;
+                }
+                Ok(())
+            }
+            ValidationError::NoChildren { scene, name } => write!(
+                f,
+                "{}: node {} has no mesh or child nodes, so there is nothing to tag",
+                scene, name
+            ),
+            ValidationError::DuplicateName { scene, name, count } => write!(
+                f,
+                "{}: {} nodes are named {}, only one of them will behave",
+                scene, count, name
+            ),
+        }
+    }
+}
+
+fn color_name(color: StatueColor) -> &'static str {
+    match color {
+        StatueColor::Red => "Red",
+        StatueColor::Green => "Green",
+        StatueColor::Blue => "Blue",
+    }
+}
+
+fn holder_name(location: Location) -> &'static str {
+    match location {
+        Location::Left => "LeftStatueHolder",
+        Location::Middle => "MiddleStatueHolder",
+        Location::Right => "RightStatueHolder",
+    }
+}
+
+fn placed_suffix(location: Location) -> &'static str {
+    match location {
+        Location::Left => "Left",
+        Location::Middle => "Mid",
+        Location::Right => "Right",
+    }
+}
+
+/// Nodes a room needs: the fixed puzzle props, a holder for every location
+/// in the solution, every statue in the solution and a placed copy of each
+/// of those statues on each holder, plus the room's doors, scripted nodes and
+/// nodes its goals slide open.
+pub fn required_room_nodes(
+    puzzle: &PuzzleDefinition,
+    room: Option<&RoomDefinition>,
+) -> Vec<String> {
+    let mut required: Vec<String> = ROOM_SCENE_NODES
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+    let mut locations: Vec<_> = puzzle.solution.keys().copied().collect();
+    locations.sort_by_key(|location| holder_name(*location));
+    let mut colors: Vec<_> = puzzle.solution.values().copied().collect();
+    colors.sort_by_key(|color| color_name(*color));
+    colors.dedup();
+    for location in locations.iter() {
+        required.push(holder_name(*location).to_string());
+    }
+    for color in colors.iter() {
+        required.push(format!("BallStatue{}", color_name(*color)));
+        for location in locations.iter() {
+            required.push(format!(
+                "BallStatue{}Placed{}",
+                color_name(*color),
+                placed_suffix(*location)
+            ));
+        }
+    }
+    if let Some(room) = room {
+        required.extend(room.doors.iter().map(|link| link.door.clone()));
+        let mut scripted: Vec<_> = room.scripts.keys().cloned().collect();
+        scripted.sort();
+        required.extend(scripted);
+        for goal in room.goals.iter() {
+            for change in goal.unlocks.iter() {
+                if let WorldChange::Slide { node, .. } = change {
+                    required.push(node.clone());
+                }
+            }
+        }
+    }
+    required
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Checks `nodes` against `required`. Duplicates are reported for every
+/// name, not just the required ones, since tagging matches on names alone.
+pub fn validate_scene(
+    scene: &str,
+    nodes: &[SceneNode],
+    required: &[String],
+) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let mut by_name: HashMap<&str, Vec<&SceneNode>> = HashMap::new();
+    for node in nodes {
+        by_name.entry(node.name.as_str()).or_default().push(node);
+    }
+
+    for name in required {
+        match by_name.get(name.as_str()) {
+            Some(found) => {
+                if !found.iter().any(|node| node.has_children) {
+                    errors.push(ValidationError::NoChildren {
+                        scene: scene.to_string(),
+                        name: name.clone(),
+                    });
+                }
+            }
+            None => {
+                let similar = by_name
+                    .keys()
+                    .map(|candidate| (edit_distance(name, candidate), *candidate))
+                    .filter(|(distance, _)| *distance <= 3)
+                    .min()
+                    .map(|(_, candidate)| candidate.to_string());
+                errors.push(ValidationError::MissingNode {
+                    scene: scene.to_string(),
+                    name: name.clone(),
+                    similar,
+                });
+            }
+        }
+    }
+
+    let mut duplicates: Vec<_> = by_name
+        .iter()
+        .filter(|(_, found)| found.len() > 1)
+        .collect();
+    duplicates.sort_by_key(|(name, _)| *name);
+    for (name, found) in duplicates {
+        errors.push(ValidationError::DuplicateName {
+            scene: scene.to_string(),
+            name: name.to_string(),
+            count: found.len(),
+        });
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str) -> SceneNode {
+        SceneNode {
+            name: name.to_string(),
+            has_children: true,
+        }
+    }
+
+    fn required(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn edit_distance_counts_single_character_edits() {
+        assert_eq!(edit_distance("InsideDoor", "InsideDoor"), 0);
+        assert_eq!(edit_distance("InsideDoor", "InsideDoor.001"), 4);
+        assert_eq!(edit_distance("RedPoster", "RedPostre"), 2);
+        assert_eq!(edit_distance("", "Button"), 6);
+    }
+
+    #[test]
+    fn missing_node_suggests_a_close_name() {
+        let errors = validate_scene("room", &[node("RedPostr")], &required(&["RedPoster"]));
+        assert_eq!(
+            errors,
+            vec![ValidationError::MissingNode {
+                scene: "room".to_string(),
+                name: "RedPoster".to_string(),
+                similar: Some("RedPostr".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn missing_node_without_a_close_name() {
+        let errors = validate_scene("room", &[node("Button")], &required(&["InsideDoor"]));
+        assert_eq!(
+            errors,
+            vec![ValidationError::MissingNode {
+                scene: "room".to_string(),
+                name: "InsideDoor".to_string(),
+                similar: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn required_node_without_children() {
+        let empty = SceneNode {
+            name: "InsideDoor".to_string(),
+            has_children: false,
+        };
+        let errors = validate_scene("room", &[empty], &required(&["InsideDoor"]));
+        assert_eq!(
+            errors,
+            vec![ValidationError::NoChildren {
+                scene: "room".to_string(),
+                name: "InsideDoor".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn duplicates_are_reported_for_any_name() {
+        let nodes = [node("Button"), node("Lamp"), node("Button"), node("Lamp")];
+        let errors = validate_scene("room", &nodes, &required(&["Button"]));
+        assert_eq!(
+            errors,
+            vec![
+                ValidationError::DuplicateName {
+                    scene: "room".to_string(),
+                    name: "Button".to_string(),
+                    count: 2,
+                },
+                ValidationError::DuplicateName {
+                    scene: "room".to_string(),
+                    name: "Lamp".to_string(),
+                    count: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn room_nodes_follow_the_solution() {
+        let puzzle = PuzzleDefinition {
+            solution: [(Location::Left, StatueColor::Red)]
+                .iter()
+                .copied()
+                .collect(),
+        };
+        let nodes = required_room_nodes(&puzzle, None);
+        assert!(nodes.contains(&"LeftStatueHolder".to_string()));
+        assert!(nodes.contains(&"BallStatueRedPlacedLeft".to_string()));
+        assert!(!nodes.contains(&"MiddleStatueHolder".to_string()));
+        assert!(!nodes.contains(&"BallStatueBlue".to_string()));
+    }
+}
@@ -0,0 +1,75 @@
+campaign-title = Escape Room
+room-classroom = Klassenzimmer
+room-classroom_2 = Klassenzimmer, nochmal
+
+menu-resume = Fortsetzen
+menu-new-game = Neues Spiel
+menu-play = Spielen
+menu-replay = Nochmal spielen
+menu-completed = Geschafft
+menu-locked = Gesperrt
+
+loading-title = Laden
+loading-progress = { $loaded } von { $total } Dateien
+loading-failed = Diese Dateien konnten nicht geladen werden:
+
+inventory-title = Inventar
+item-InvBallStatueRed = Rote Statue
+item-InvBallStatueGreen = Grüne Statue
+item-InvBallStatueBlue = Blaue Statue
+item-InvBlacklightFlashlight = Schwarzlicht-Taschenlampe
+
+settings-title = Einstellungen
+settings-controls = Steuerung
+settings-fov = Sichtfeld
+settings-sensitivity = Mausempfindlichkeit
+settings-speed = Laufgeschwindigkeit
+settings-audio = Ton
+settings-volume = Lautstärke
+settings-effects = Effekte
+settings-ambience = Atmosphäre
+settings-mute = Stumm (M)
+settings-graphics = Grafik
+settings-vsync = VSync
+settings-antialiasing = Kantenglättung
+settings-antialiasing-off = Aus
+settings-antialiasing-restart = Kantenglättung wird nach einem Neustart übernommen.
+settings-language = Sprache
+settings-reset = Zurücksetzen
+
+achievement-unlocked = Erfolg freigeschaltet
+achievements-title = Erfolge { $unlocked }/{ $total }
+achievement-no_hints = Ohne Hinweise
+achievement-no_hints-description = Löse einen Raum, ohne ein Poster mit dem Schwarzlicht anzuleuchten.
+achievement-first_try = Auf Anhieb
+achievement-first_try-description = Löse einen Raum ohne falschen Versuch.
+achievement-under_five_minutes = Unter 5 Minuten
+achievement-under_five_minutes-description = Löse einen Raum innerhalb von fünf Minuten.
+achievement-every_poster = Alle UV-Poster
+achievement-every_poster-description = Decke alle drei Poster in einem Raum auf.
+
+settings-accessibility = Barrierefreiheit
+settings-color-symbols = Farben mit Formen markieren
+settings-color-names = Farbnamen anzeigen
+settings-high-contrast = Kontrastreiche Anzeige
+
+color-red = Rot
+color-green = Grün
+color-blue = Blau
+settings-subtitles = Untertitel
+settings-reduced-motion = Bewegung reduzieren
+settings-ui-scale = Oberflächengröße
+
+caption-pickup = [hebt etwas auf]
+caption-place = [Stein poltert auf Holz]
+caption-wrong = [tiefes Summen]
+caption-door-creak = [Tür knarrt auf]
+caption-blacklight-hum = [Schwarzlicht summt]
+
+clue-storage-tub = Irgendetwas klappert in der Kiste.
+
+touch-use = Nutzen
+
+pause-title = Pausiert
+pause-resume = Weiter
+pointer-lock-unavailable = Dieser Browser kann die Maus nicht sperren, schau dich mit Q/E und R/F um.
@@ -0,0 +1,77 @@
+# English, also the fallback for keys missing from other languages.
+
+campaign-title = Escape Room
+room-classroom = Classroom
+room-classroom_2 = Classroom, again
+
+menu-resume = Resume
+menu-new-game = New game
+menu-play = Play
+menu-replay = Replay
+menu-completed = Completed
+menu-locked = Locked
+
+loading-title = Loading
+loading-progress = { $loaded } of { $total } assets
+loading-failed = These assets failed to load:
+
+inventory-title = Inventory
+item-InvBallStatueRed = Red statue
+item-InvBallStatueGreen = Green statue
+item-InvBallStatueBlue = Blue statue
+item-InvBlacklightFlashlight = Blacklight flashlight
+
+settings-title = Settings
+settings-controls = Controls
+settings-fov = Field of view
+settings-sensitivity = Mouse sensitivity
+settings-speed = Move speed
+settings-audio = Audio
+settings-volume = Volume
+settings-effects = Effects
+settings-ambience = Ambience
+settings-mute = Mute (M)
+settings-graphics = Graphics
+settings-vsync = VSync
+settings-antialiasing = Anti-aliasing
+settings-antialiasing-off = Off
+settings-antialiasing-restart = Anti-aliasing changes apply after a restart.
+settings-language = Language
+settings-reset = Reset to defaults
+
+achievement-unlocked = Achievement unlocked
+achievements-title = Achievements { $unlocked }/{ $total }
+achievement-no_hints = Solve without hints
+achievement-no_hints-description = Solve a room without shining the blacklight on a poster.
+achievement-first_try = First try
+achievement-first_try-description = Solve a room without a wrong attempt.
+achievement-under_five_minutes = Escape under 5 minutes
+achievement-under_five_minutes-description = Solve a room within five minutes of walking in.
+achievement-every_poster = Find every UV poster
+achievement-every_poster-description = Reveal all three posters in a room.
+
+settings-accessibility = Accessibility
+settings-color-symbols = Mark colors with shapes
+settings-color-names = Show color names
+settings-high-contrast = High contrast HUD
+
+color-red = Red
+color-green = Green
+color-blue = Blue
+settings-subtitles = Subtitles
+settings-reduced-motion = Reduce motion
+settings-ui-scale = UI scale
+
+caption-pickup = [picks something up]
+caption-place = [thud of stone on wood]
+caption-wrong = [low buzz]
+caption-door-creak = [door creaks open]
+caption-blacklight-hum = [blacklight hums]
+
+clue-storage-tub = Something rattles inside the tub.
+
+touch-use = Use
+
+pause-title = Paused
+pause-resume = Resume
+pointer-lock-unavailable = This browser can't lock the mouse, look around with Q/E and R/F.